@@ -39,6 +39,16 @@ pub struct EventPacket {
 }
 
 impl EventPacket {
+  /// Create a new `EventPacket`.
+  #[inline]
+  pub const fn new(command: EventType, request: RequestID, payload: Payload) -> Self {
+    Self {
+      command,
+      request,
+      payload,
+    }
+  }
+
   /// Get the type identifier of the event.
   #[inline]
   pub const fn command(&self) -> EventType {
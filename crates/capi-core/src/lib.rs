@@ -5,6 +5,7 @@ pub mod packet;
 pub mod payload;
 pub mod request;
 pub mod response;
+pub mod stage;
 pub mod types;
 
 pub use self::event::EventPacket;
@@ -18,3 +19,4 @@ pub use self::request::RequestType;
 pub use self::response::ResponsePacket;
 pub use self::response::ResponseStatus;
 pub use self::response::ResponseType;
+pub use self::stage::Stage;
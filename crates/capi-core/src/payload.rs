@@ -43,6 +43,18 @@ impl Payload {
   {
     self.inner.insert(k.into(), v.into());
   }
+
+  /// Get the value of `key`, if present.
+  #[inline]
+  pub fn get(&self, key: &str) -> Option<&Value> {
+    self.inner.get(key)
+  }
+
+  /// Get a mutable reference to the value of `key`, if present.
+  #[inline]
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+    self.inner.get_mut(key)
+  }
 }
 
 impl Default for Payload {
@@ -10,6 +10,7 @@ use std::sync::atomic::Ordering;
 use crate::packet::IntoPayload;
 use crate::packet::Packet;
 use crate::payload::Payload;
+use crate::response::ResponseType;
 
 // =============================================================================
 // Request Type
@@ -39,6 +40,34 @@ pub enum RequestType {
   SetModerator,
 }
 
+impl RequestType {
+  /// Returns `true` if the request is a moderation command.
+  #[inline]
+  pub const fn is_moderation(&self) -> bool {
+    matches!(
+      self,
+      Self::BanUser | Self::UnbanUser | Self::KickUser | Self::SetModerator
+    )
+  }
+
+  /// Returns the type of the response to the request.
+  #[inline]
+  pub const fn response(&self) -> ResponseType {
+    match self {
+      Self::Authenticate => ResponseType::Authenticate,
+      Self::Connect => ResponseType::Connect,
+      Self::Disconnect => ResponseType::Disconnect,
+      Self::SendMessage => ResponseType::SendMessage,
+      Self::SendWhisper => ResponseType::SendWhisper,
+      Self::BanUser => ResponseType::BanUser,
+      Self::UnbanUser => ResponseType::UnbanUser,
+      Self::SendEmote => ResponseType::SendEmote,
+      Self::KickUser => ResponseType::KickUser,
+      Self::SetModerator => ResponseType::SetModerator,
+    }
+  }
+}
+
 // =============================================================================
 // Request ID
 // =============================================================================
@@ -48,6 +77,16 @@ pub enum RequestType {
 pub struct RequestID(u64);
 
 impl RequestID {
+  #[inline]
+  pub const fn new(value: u64) -> Self {
+    Self(value)
+  }
+
+  #[inline]
+  pub const fn get(&self) -> u64 {
+    self.0
+  }
+
   fn next() -> Self {
     static NEXT: AtomicU64 = AtomicU64::new(1);
     Self(NEXT.fetch_add(1, Ordering::SeqCst))
@@ -96,4 +135,20 @@ impl RequestPacket {
   pub const fn payload(&self) -> &Payload {
     &self.payload
   }
+
+  /// Get a mutable reference to the payload of the request.
+  #[inline]
+  pub fn payload_mut(&mut self) -> &mut Payload {
+    &mut self.payload
+  }
+
+  /// Create a copy of the request with a new request ID.
+  #[inline]
+  pub fn renew(&self) -> Self {
+    Self {
+      command: self.command,
+      request: RequestID::next(),
+      payload: self.payload.clone(),
+    }
+  }
 }
@@ -47,6 +47,18 @@ pub struct ResponseStatus {
 }
 
 impl ResponseStatus {
+  pub const OK: Self = Self::new(0, 0);
+  pub const NOT_CONNECTED: Self = Self::new(8, 1);
+  pub const BAD_REQUEST: Self = Self::new(8, 2);
+  pub const TIMED_OUT: Self = Self::new(6, 5);
+  pub const RATE_LIMITED: Self = Self::new(6, 8);
+
+  /// Create a new `ResponseStatus` from the given `area` and `code`.
+  #[inline]
+  pub const fn new(area: u8, code: u8) -> Self {
+    Self { area, code }
+  }
+
   #[inline]
   pub const fn area(&self) -> u8 {
     self.area
@@ -57,6 +69,18 @@ impl ResponseStatus {
     self.code
   }
 
+  /// Returns `true` if the status reports success.
+  #[inline]
+  pub const fn is_ok(&self) -> bool {
+    self.area == 0 && self.code == 0
+  }
+
+  /// Returns `true` if the status reports a hit rate limit.
+  #[inline]
+  pub const fn is_rate_limited(&self) -> bool {
+    self.area == 6 && self.code == 8
+  }
+
   #[inline]
   pub const fn as_str(&self) -> &'static str {
     match (self.area, self.code) {
@@ -84,6 +108,22 @@ pub struct ResponsePacket {
 }
 
 impl ResponsePacket {
+  /// Create a new `ResponsePacket`.
+  #[inline]
+  pub const fn new(
+    command: ResponseType,
+    request: RequestID,
+    payload: Payload,
+    status: Option<ResponseStatus>,
+  ) -> Self {
+    Self {
+      command,
+      request,
+      payload,
+      status,
+    }
+  }
+
   /// Get the type identifier of the response.
   #[inline]
   pub const fn command(&self) -> ResponseType {
@@ -0,0 +1,47 @@
+use crate::request::RequestType;
+use crate::response::ResponseStatus;
+
+// =============================================================================
+// Session Stage
+// =============================================================================
+
+/// The progress of a session through the chat API handshake.
+///
+/// Sessions start [`Unauthenticated`][Self::Unauthenticated]. An authenticate
+/// request moves them to [`Authenticated`][Self::Authenticated], a connect
+/// request to [`InChat`][Self::InChat], and a disconnect request back.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+  /// Waiting for an authenticate request.
+  Unauthenticated,
+  /// Authenticated, but not in chat.
+  Authenticated,
+  /// Connected to chat.
+  InChat,
+}
+
+impl Stage {
+  /// Check that a request of type `command` is valid in this stage.
+  ///
+  /// Returns the status the server answers an invalid request with.
+  pub const fn check(self, command: RequestType) -> Result<(), ResponseStatus> {
+    match (command, self) {
+      (RequestType::Authenticate, Self::Unauthenticated) => Ok(()),
+      (_, Self::Unauthenticated) => Err(ResponseStatus::BAD_REQUEST),
+      (RequestType::Authenticate, _) => Err(ResponseStatus::BAD_REQUEST),
+      (RequestType::Connect, Self::Authenticated) => Ok(()),
+      (RequestType::Connect, _) => Err(ResponseStatus::BAD_REQUEST),
+      (_, Self::Authenticated) => Err(ResponseStatus::NOT_CONNECTED),
+      (_, Self::InChat) => Ok(()),
+    }
+  }
+
+  /// Returns the stage after a successful request of type `command`.
+  pub const fn next(self, command: RequestType) -> Self {
+    match command {
+      RequestType::Authenticate | RequestType::Disconnect => Self::Authenticated,
+      RequestType::Connect => Self::InChat,
+      _ => self,
+    }
+  }
+}
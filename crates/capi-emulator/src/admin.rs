@@ -0,0 +1,219 @@
+use capi_core::RequestType;
+use capi_socket::transport::mock::MockServer;
+use serde_json::from_str;
+use serde_json::from_value;
+use serde_json::Value;
+use std::str::FromStr;
+use std::str::SplitWhitespace;
+use tokio::io::stdin;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::BufReader;
+use tokio::io::Lines;
+use tokio::io::Stdin;
+
+use crate::scenario::Action;
+
+const HELP: &str = "\
+commands:
+  join <user_id> <toon_name> [flags...]
+  leave <user_id>
+  flags <user_id> [flags...]
+  say <user_id> <message>
+  whisper <user_id> <message>
+  emote <user_id> <message>
+  status <command> <area> <code>
+  event <json>
+  users
+  requests
+  help
+  {\"action\": ...}  (any scenario script action)";
+
+// =============================================================================
+// Admin Console
+// =============================================================================
+
+/// Read admin commands from stdin until it is closed.
+pub async fn run(server: MockServer) {
+  let mut lines: Lines<BufReader<Stdin>> = BufReader::new(stdin()).lines();
+
+  while let Ok(Some(line)) = lines.next_line().await {
+    let line: &str = line.trim();
+
+    if line.is_empty() {
+      continue;
+    }
+
+    match line {
+      "help" => eprintln!("{HELP}"),
+      "users" => eprintln!("{:?}", server.users()),
+      "requests" => {
+        for request in server.requests() {
+          eprintln!("{request:?}");
+        }
+      }
+      _ => match parse(line) {
+        Ok(action) => action.apply(&server),
+        Err(error) => eprintln!("{error} (try `help`)"),
+      },
+    }
+  }
+}
+
+// =============================================================================
+// Command Parser
+// =============================================================================
+
+/// Parse a command line into an [`Action`].
+fn parse(line: &str) -> Result<Action, String> {
+  if line.starts_with('{') {
+    return from_str(line).map_err(|error| error.to_string());
+  }
+
+  let (command, rest): (&str, &str) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+  let rest: &str = rest.trim_start();
+  let mut args: SplitWhitespace<'_> = rest.split_whitespace();
+
+  match command {
+    "join" => Ok(Action::Join {
+      user_id: user_id(&mut args)?,
+      toon_name: args.next().ok_or("missing toon name")?.to_owned(),
+      flags: args.map(str::to_owned).collect(),
+    }),
+    "leave" => Ok(Action::Leave {
+      user_id: user_id(&mut args)?,
+    }),
+    "flags" => Ok(Action::Flags {
+      user_id: user_id(&mut args)?,
+      flags: args.map(str::to_owned).collect(),
+    }),
+    "say" | "whisper" | "emote" => {
+      let user_id: u64 = user_id(&mut args)?;
+      let message: String = message(rest)?;
+
+      Ok(match command {
+        "say" => Action::Say { user_id, message },
+        "whisper" => Action::Whisper { user_id, message },
+        _ => Action::Emote { user_id, message },
+      })
+    }
+    "status" => Ok(Action::Status {
+      command: request_type(args.next().ok_or("missing command")?)?,
+      area: number(args.next(), "area")?,
+      code: number(args.next(), "code")?,
+    }),
+    "event" => Ok(Action::Event {
+      event: from_str(rest).map_err(|error| error.to_string())?,
+    }),
+    _ => Err(format!("unknown command `{command}`")),
+  }
+}
+
+fn user_id(args: &mut SplitWhitespace<'_>) -> Result<u64, String> {
+  number(args.next(), "user id")
+}
+
+fn number<T: FromStr>(arg: Option<&str>, name: &str) -> Result<T, String> {
+  arg
+    .ok_or_else(|| format!("missing {name}"))?
+    .parse()
+    .map_err(|_| format!("invalid {name}"))
+}
+
+/// Returns the text following the user ID.
+fn message(rest: &str) -> Result<String, String> {
+  let message: &str = rest
+    .split_once(char::is_whitespace)
+    .map_or("", |(_, message)| message.trim());
+
+  if message.is_empty() {
+    return Err("missing message".to_owned());
+  }
+
+  Ok(message.to_owned())
+}
+
+/// Parse a request type from its wire name or short name, e.g. `SendMessage`.
+fn request_type(name: &str) -> Result<RequestType, String> {
+  let names: [String; 3] = [
+    name.to_owned(),
+    format!("Botapichat.{name}Request"),
+    format!("Botapiauth.{name}Request"),
+  ];
+
+  names
+    .into_iter()
+    .find_map(|name| from_value(Value::String(name)).ok())
+    .ok_or_else(|| format!("unknown request type `{name}`"))
+}
+
+#[cfg(test)]
+mod tests {
+  use capi_core::RequestType;
+
+  use super::parse;
+  use crate::scenario::Action;
+
+  #[test]
+  fn parses_join_with_flags() {
+    let action: Action = parse("join 7 Bob#1 Moderator Speaker").unwrap();
+
+    assert!(matches!(
+      action,
+      Action::Join { user_id: 7, ref toon_name, ref flags }
+        if toon_name == "Bob#1" && flags == &["Moderator", "Speaker"]
+    ));
+  }
+
+  #[test]
+  fn keeps_the_whole_message() {
+    let action: Action = parse("say 7  hello   there ").unwrap();
+
+    assert!(matches!(
+      action,
+      Action::Say { user_id: 7, ref message } if message == "hello   there"
+    ));
+  }
+
+  #[test]
+  fn parses_request_types_by_short_and_wire_name() {
+    let short: Action = parse("status SendMessage 6 8").unwrap();
+    let wire: Action = parse("status Botapiauth.AuthenticateRequest 8 1").unwrap();
+
+    assert!(matches!(
+      short,
+      Action::Status {
+        command: RequestType::SendMessage,
+        area: 6,
+        code: 8
+      }
+    ));
+    assert!(matches!(
+      wire,
+      Action::Status {
+        command: RequestType::Authenticate,
+        area: 8,
+        code: 1
+      }
+    ));
+  }
+
+  #[test]
+  fn parses_script_actions() {
+    let action: Action = parse(r#"{ "action": "leave", "user_id": 7 }"#).unwrap();
+
+    assert!(matches!(action, Action::Leave { user_id: 7 }));
+  }
+
+  #[test]
+  fn reports_invalid_commands() {
+    assert_eq!(parse("dance").unwrap_err(), "unknown command `dance`");
+    assert_eq!(parse("say 7").unwrap_err(), "missing message");
+    assert_eq!(parse("leave bob").unwrap_err(), "invalid user id");
+    assert_eq!(parse("join 7").unwrap_err(), "missing toon name");
+    assert_eq!(
+      parse("status Dance 6 8").unwrap_err(),
+      "unknown request type `Dance`"
+    );
+    assert_eq!(parse("status SendMessage 6").unwrap_err(), "missing code");
+  }
+}
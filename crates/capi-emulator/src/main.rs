@@ -0,0 +1,217 @@
+//! A local emulator of the Battle.net Chat API.
+//!
+//! Serves the chat API over plain WebSocket (`ws://`), so bots can be run
+//! against a scripted channel without touching the real service. Terminate TLS
+//! in front of the emulator if a bot insists on `wss://`.
+//!
+//! ```text
+//! capi-emulator [--bind ADDR] [--scenario FILE] [--api-key KEY]
+//! ```
+//!
+//! Every connection is an independent bot session in the same channel. The
+//! channel, its users, the per-connection rate limit, and a script of timed
+//! events are read from a JSON scenario file, described in `scenario.rs`.
+//!
+//! While running, admin commands are read from stdin to inject events, e.g.
+//! `say 1 hello` or `status SendMessage 6 8`; type `help` for the full list.
+
+use capi_socket::channel::Control;
+use capi_socket::channel::Frame;
+use capi_socket::channel::Message as _;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use std::env;
+use std::error::Error;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::net::SocketAddrV4;
+use std::path::PathBuf;
+use std::process;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::scenario::Scenario;
+
+mod admin;
+mod scenario;
+
+const USAGE: &str = "usage: capi-emulator [--bind ADDR] [--scenario FILE] [--api-key KEY]";
+
+// =============================================================================
+// Options
+// =============================================================================
+
+struct Options {
+  bind: SocketAddr,
+  scenario: Option<PathBuf>,
+  api_key: Option<String>,
+}
+
+impl Options {
+  const DEFAULT_BIND: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8080));
+
+  fn parse() -> Result<Self, String> {
+    let mut this: Self = Self {
+      bind: Self::DEFAULT_BIND,
+      scenario: None,
+      api_key: None,
+    };
+
+    let mut args: env::Args = env::args();
+
+    args.next();
+
+    while let Some(arg) = args.next() {
+      let mut value = || {
+        args
+          .next()
+          .ok_or_else(|| format!("missing value for `{arg}`"))
+      };
+
+      match arg.as_str() {
+        "--bind" => {
+          this.bind = value()?
+            .parse()
+            .map_err(|error| format!("invalid address: {error}"))?;
+        }
+        "--scenario" => {
+          this.scenario = Some(PathBuf::from(value()?));
+        }
+        "--api-key" => {
+          this.api_key = Some(value()?);
+        }
+        "--help" | "-h" => {
+          println!("{USAGE}");
+          process::exit(0);
+        }
+        _ => {
+          return Err(format!("unexpected argument `{arg}`"));
+        }
+      }
+    }
+
+    Ok(this)
+  }
+}
+
+// =============================================================================
+// Main
+// =============================================================================
+
+#[tokio::main]
+async fn main() {
+  let options: Options = match Options::parse() {
+    Ok(options) => options,
+    Err(error) => {
+      eprintln!("{error}\n{USAGE}");
+      process::exit(2);
+    }
+  };
+
+  if let Err(error) = run(options).await {
+    eprintln!("error: {error}");
+    process::exit(1);
+  }
+}
+
+async fn run(options: Options) -> Result<(), Box<dyn Error>> {
+  let mut scenario: Scenario = match options.scenario {
+    Some(ref path) => Scenario::load(path)?,
+    None => Scenario::default(),
+  };
+
+  if let Some(api_key) = options.api_key {
+    scenario.api_key = api_key;
+  }
+
+  let server: MockServer = scenario.server();
+  let listener: TcpListener = TcpListener::bind(options.bind).await?;
+
+  eprintln!(
+    "serving channel {:?} at ws://{}/v1/rpc/chat",
+    scenario.channel,
+    listener.local_addr()?,
+  );
+
+  tokio::spawn(scenario.run(server.clone()));
+  tokio::spawn(admin::run(server.clone()));
+
+  loop {
+    let (stream, peer): (TcpStream, SocketAddr) = listener.accept().await?;
+
+    tokio::spawn(serve(server.clone(), stream, peer));
+  }
+}
+
+// =============================================================================
+// Connection
+// =============================================================================
+
+/// Bridge a WebSocket connection to a new session of `server`.
+async fn serve(server: MockServer, stream: TcpStream, peer: SocketAddr) {
+  let mut socket: WebSocketStream<TcpStream> = match tokio_tungstenite::accept_async(stream).await {
+    Ok(socket) => socket,
+    Err(error) => {
+      eprintln!("{peer}: handshake failed: {error}");
+      return;
+    }
+  };
+
+  let mut session: Memory = server.transport();
+
+  eprintln!("{peer}: connected");
+
+  loop {
+    tokio::select! {
+      message = socket.next() => {
+        let Some(Ok(message)) = message else {
+          break;
+        };
+
+        // tungstenite answers pings itself
+        match message.classify() {
+          Ok(Frame::Control(_)) => {}
+          Ok(frame @ Frame::Data(_)) => {
+            if session.push(frame).is_err() {
+              break;
+            }
+          }
+          Ok(Frame::Close(_)) | Err(_) => {
+            break;
+          }
+        }
+      }
+      frame = session.next() => {
+        let Some(Ok(frame)) = frame else {
+          break;
+        };
+
+        if socket.send(into_message(frame)).await.is_err() {
+          break;
+        }
+      }
+    }
+  }
+
+  let _ignore = socket.close(None).await;
+
+  eprintln!("{peer}: disconnected");
+}
+
+fn into_message(frame: Frame) -> Message {
+  match frame {
+    Frame::Data(data) => Message::Text(data),
+    Frame::Control(Control::Ping(data)) => Message::Ping(data),
+    Frame::Control(Control::Pong(data)) => Message::Pong(data),
+    Frame::Close(frame) => Message::Close(frame.map(|frame| CloseFrame {
+      code: CloseCode::from(frame.code),
+      reason: frame.reason.into(),
+    })),
+  }
+}
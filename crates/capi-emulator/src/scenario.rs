@@ -0,0 +1,365 @@
+use capi_core::types::UserID;
+use capi_core::EventPacket;
+use capi_core::RequestType;
+use capi_core::ResponseStatus;
+use capi_socket::channel::RateLimit;
+use capi_socket::transport::mock::MockServer;
+use serde::Deserialize;
+use serde_json::from_reader;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time;
+
+// =============================================================================
+// Scenario
+// =============================================================================
+
+/// The channel served by the emulator, loaded from a JSON file.
+///
+/// ```json
+/// {
+///   "api_key": "secret",
+///   "channel": "Op Emulator",
+///   "users": [{ "user_id": 1, "toon_name": "Alice#1234", "flags": ["Moderator"] }],
+///   "rate_limit": { "capacity": 5, "period_ms": 10000 },
+///   "repeat": true,
+///   "script": [{ "delay_ms": 5000, "action": "say", "user_id": 1, "message": "hi" }]
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+  pub api_key: String,
+  pub channel: String,
+  pub users: Vec<User>,
+  pub rate_limit: Option<Limit>,
+  pub repeat: bool,
+  pub script: Vec<Step>,
+}
+
+impl Scenario {
+  pub const DEFAULT_API_KEY: &'static str = "emulator";
+  pub const DEFAULT_CHANNEL: &'static str = "Op Emulator";
+
+  pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+    let file: BufReader<File> = BufReader::new(File::open(path)?);
+    let this: Self = from_reader(file)?;
+
+    Ok(this)
+  }
+
+  /// Create a server with the channel, users, and rate limit of the scenario.
+  pub fn server(&self) -> MockServer {
+    let server: MockServer = MockServer::new(self.api_key.as_str(), self.channel.as_str());
+
+    server.set_rate_limit(self.rate_limit.map(Limit::get));
+
+    for user in self.users.iter() {
+      Action::Join {
+        user_id: user.user_id,
+        toon_name: user.toon_name.clone(),
+        flags: user.flags.clone(),
+      }
+      .apply(&server);
+    }
+
+    server
+  }
+
+  /// Run the script of the scenario against `server`.
+  pub async fn run(self, server: MockServer) {
+    if self.script.is_empty() {
+      return;
+    }
+
+    loop {
+      for step in self.script.iter() {
+        time::sleep(Duration::from_millis(step.delay_ms)).await;
+        step.action.apply(&server);
+      }
+
+      if !self.repeat {
+        break;
+      }
+    }
+  }
+}
+
+impl Default for Scenario {
+  fn default() -> Self {
+    Self {
+      api_key: Self::DEFAULT_API_KEY.to_owned(),
+      channel: Self::DEFAULT_CHANNEL.to_owned(),
+      users: Vec::new(),
+      rate_limit: None,
+      repeat: false,
+      script: Vec::new(),
+    }
+  }
+}
+
+/// A user present in the channel from the start.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct User {
+  pub user_id: u64,
+  pub toon_name: String,
+  #[serde(default)]
+  pub flags: Vec<String>,
+}
+
+/// The request budget of each bot connection.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limit {
+  pub capacity: u32,
+  pub period_ms: u64,
+}
+
+impl Limit {
+  #[inline]
+  pub const fn get(self) -> RateLimit {
+    RateLimit::new(self.capacity, Duration::from_millis(self.period_ms))
+  }
+}
+
+/// A scripted action, run `delay_ms` after the previous one.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Step {
+  #[serde(default)]
+  pub delay_ms: u64,
+  #[serde(flatten)]
+  pub action: Action,
+}
+
+// =============================================================================
+// Action
+// =============================================================================
+
+/// A change to the emulated channel, from the script or an admin command.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Action {
+  Join {
+    user_id: u64,
+    toon_name: String,
+    #[serde(default)]
+    flags: Vec<String>,
+  },
+  Leave {
+    user_id: u64,
+  },
+  Flags {
+    user_id: u64,
+    flags: Vec<String>,
+  },
+  Say {
+    user_id: u64,
+    message: String,
+  },
+  Whisper {
+    user_id: u64,
+    message: String,
+  },
+  Emote {
+    user_id: u64,
+    message: String,
+  },
+  /// Answer the next request of type `command` with the given status.
+  Status {
+    command: RequestType,
+    area: u8,
+    code: u8,
+  },
+  /// Send an arbitrary event to every bot in chat.
+  Event {
+    event: EventPacket,
+  },
+}
+
+impl Action {
+  pub fn apply(&self, server: &MockServer) {
+    match self {
+      Self::Join {
+        user_id,
+        toon_name,
+        flags,
+      } => {
+        let user_id: UserID = UserID::new(*user_id);
+
+        if !server.join(user_id, toon_name) {
+          eprintln!("{toon_name} is banned");
+          return;
+        }
+
+        if !flags.is_empty() {
+          let flags: Vec<&str> = flags.iter().map(String::as_str).collect();
+          server.set_flags(user_id, &flags);
+        }
+      }
+      Self::Leave { user_id } => {
+        server.leave(UserID::new(*user_id));
+      }
+      Self::Flags { user_id, flags } => {
+        let flags: Vec<&str> = flags.iter().map(String::as_str).collect();
+        server.set_flags(UserID::new(*user_id), &flags);
+      }
+      Self::Say { user_id, message } => {
+        server.say(UserID::new(*user_id), message);
+      }
+      Self::Whisper { user_id, message } => {
+        server.whisper(UserID::new(*user_id), message);
+      }
+      Self::Emote { user_id, message } => {
+        server.emote(UserID::new(*user_id), message);
+      }
+      Self::Status {
+        command,
+        area,
+        code,
+      } => {
+        server.respond_with(*command, ResponseStatus::new(*area, *code));
+      }
+      Self::Event { event } => {
+        server.inject(event);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use capi_core::types::UserID;
+  use capi_core::RequestType;
+  use capi_core::ResponseStatus;
+  use capi_socket::channel::Channel;
+  use capi_socket::channel::ChannelConfig;
+  use capi_socket::channel::ChannelError;
+  use capi_socket::transport::memory::Memory;
+  use capi_socket::transport::mock::MockServer;
+  use capi_socket::SocketExt;
+  use serde_json::from_str;
+  use std::env;
+  use std::error::Error;
+  use std::fs;
+  use std::path::PathBuf;
+  use std::process;
+  use std::time::Duration;
+
+  use super::Action;
+  use super::Scenario;
+
+  const EXAMPLE: &str = r#"{
+    "api_key": "secret",
+    "channel": "Op Emulator",
+    "users": [{ "user_id": 1, "toon_name": "Alice#1234", "flags": ["Moderator"] }],
+    "rate_limit": { "capacity": 3, "period_ms": 60000 },
+    "repeat": true,
+    "script": [{ "delay_ms": 5000, "action": "say", "user_id": 1, "message": "hi" }]
+  }"#;
+
+  /// Connects a bot that does not retry rate limited requests.
+  async fn connect(server: &MockServer, api_key: &str) -> Channel<Memory> {
+    let channel: Channel<Memory> =
+      server.connect_with_config(ChannelConfig::new().rate_limit_retries(0));
+
+    channel.send_authenticate(api_key).await.unwrap();
+    channel.send_connect().await.unwrap();
+
+    channel
+  }
+
+  #[test]
+  fn parses_every_field() {
+    let scenario: Scenario = from_str(EXAMPLE).unwrap();
+
+    assert_eq!(scenario.api_key, "secret");
+    assert_eq!(scenario.users[0].toon_name, "Alice#1234");
+    assert_eq!(scenario.users[0].flags, ["Moderator"]);
+    assert_eq!(
+      scenario.rate_limit.unwrap().get().period(),
+      Duration::from_secs(60)
+    );
+    assert!(scenario.repeat);
+    assert_eq!(scenario.script[0].delay_ms, 5000);
+    assert!(matches!(
+      scenario.script[0].action,
+      Action::Say { user_id: 1, ref message } if message == "hi"
+    ));
+  }
+
+  #[test]
+  fn missing_fields_use_defaults() {
+    let scenario: Scenario = from_str("{}").unwrap();
+
+    assert_eq!(scenario.api_key, Scenario::DEFAULT_API_KEY);
+    assert_eq!(scenario.channel, Scenario::DEFAULT_CHANNEL);
+    assert!(scenario.users.is_empty());
+    assert!(scenario.rate_limit.is_none());
+    assert!(scenario.script.is_empty());
+  }
+
+  #[test]
+  fn unknown_fields_are_rejected() {
+    assert!(from_str::<Scenario>(r#"{ "api-key": "secret" }"#).is_err());
+    assert!(from_str::<Scenario>(r#"{ "script": [{ "action": "dance" }] }"#).is_err());
+  }
+
+  #[test]
+  fn loads_from_a_file() {
+    let path: PathBuf = env::temp_dir().join(format!("capi-emulator-{}.json", process::id()));
+
+    fs::write(&path, EXAMPLE).unwrap();
+
+    let scenario: Result<Scenario, Box<dyn Error>> = Scenario::load(&path);
+
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(scenario.unwrap().channel, "Op Emulator");
+    assert!(Scenario::load(&path).is_err());
+  }
+
+  #[tokio::test]
+  async fn server_has_the_scenario_users() {
+    let scenario: Scenario = from_str(EXAMPLE).unwrap();
+    let server: MockServer = scenario.server();
+
+    assert_eq!(server.users(), [UserID::new(1)]);
+  }
+
+  #[tokio::test]
+  async fn requests_over_budget_are_rate_limited() {
+    let scenario: Scenario = from_str(EXAMPLE).unwrap();
+    let server: MockServer = scenario.server();
+    let channel: Channel<Memory> = connect(&server, "secret").await;
+
+    // Authenticate and connect took two of the three tokens.
+    channel.send_message("first").await.unwrap();
+
+    let error: ChannelError = channel.send_message("second").await.unwrap_err();
+
+    assert_eq!(error.response_status(), Some(ResponseStatus::RATE_LIMITED));
+  }
+
+  #[tokio::test]
+  async fn status_action_answers_the_next_request() {
+    let server: MockServer = Scenario::default().server();
+    let channel: Channel<Memory> = connect(&server, Scenario::DEFAULT_API_KEY).await;
+    let status: ResponseStatus = ResponseStatus::new(6, 8);
+
+    Action::Status {
+      command: RequestType::SendMessage,
+      area: 6,
+      code: 8,
+    }
+    .apply(&server);
+
+    let error: ChannelError = channel.send_message("first").await.unwrap_err();
+
+    assert_eq!(error.response_status(), Some(status));
+    assert!(channel.send_message("second").await.is_ok());
+  }
+}
@@ -0,0 +1,255 @@
+//! Routing requests to handlers.
+
+use capi_core::types::UserID;
+use capi_core::Payload;
+use capi_core::RequestID;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use serde_json::from_value;
+use serde_json::Value;
+use std::future::ready;
+use std::future::Future;
+use std::io;
+
+use crate::event;
+use crate::response;
+use crate::session::Session;
+
+/// The outcome of a request: the payload of a successful response, or the
+/// status of a failed one.
+pub type Reply = Result<Payload, ResponseStatus>;
+
+// =============================================================================
+// Dispatcher
+// =============================================================================
+
+/// Handles the requests of connected clients, one method per request type.
+///
+/// Requests reach a handler only when they are valid for the [`Stage`][capi_core::Stage] of the
+/// session and carry the fields of their type; other requests are answered by
+/// [`dispatch`] without calling the dispatcher. A successful authenticate,
+/// connect, or disconnect request moves the session to the next stage.
+///
+/// Events sent through the session from within a handler are delivered after
+/// the response to the request, e.g. the connect event following a connect
+/// response.
+///
+/// Chat requests are answered with [`BAD_REQUEST`][ResponseStatus::BAD_REQUEST]
+/// unless their handler is implemented.
+pub trait Dispatcher: Send + Sync + 'static {
+  /// Check the API key of a client.
+  fn authenticate(
+    &self,
+    session: &Session,
+    request: RequestID,
+    api_key: &str,
+  ) -> impl Future<Output = Reply> + Send;
+
+  /// Enter chat. The handler should send a [`connect`][event::connect] event,
+  /// followed by a [`user_update`][event::user_update] for each user.
+  fn connect(&self, session: &Session, request: RequestID) -> impl Future<Output = Reply> + Send;
+
+  /// Leave chat. By default, sends a [`disconnect`][event::disconnect] event.
+  fn disconnect(
+    &self,
+    session: &Session,
+    request: RequestID,
+  ) -> impl Future<Output = Reply> + Send {
+    let _ignore = session.send(&event::disconnect(request));
+    ready(Ok(Payload::new()))
+  }
+
+  /// Send `message` to the channel. The handler should
+  /// [`broadcast`][crate::Server::broadcast] a [`message`][event::message]
+  /// event of type [`Channel`][crate::MessageType::Channel].
+  fn send_message(
+    &self,
+    _session: &Session,
+    _request: RequestID,
+    _message: &str,
+  ) -> impl Future<Output = Reply> + Send {
+    unsupported()
+  }
+
+  /// Send `message` to the user `user_id` only, as a
+  /// [`Whisper`][crate::MessageType::Whisper]. Answer with
+  /// [`BAD_REQUEST`][ResponseStatus::BAD_REQUEST] if the user is not in the
+  /// channel.
+  fn send_whisper(
+    &self,
+    _session: &Session,
+    _request: RequestID,
+    _user_id: UserID,
+    _message: &str,
+  ) -> impl Future<Output = Reply> + Send {
+    unsupported()
+  }
+
+  /// Ban the user `user_id` from the channel. The handler should broadcast a
+  /// [`user_leave`][event::user_leave] event for the user.
+  fn ban_user(
+    &self,
+    _session: &Session,
+    _request: RequestID,
+    _user_id: UserID,
+  ) -> impl Future<Output = Reply> + Send {
+    unsupported()
+  }
+
+  /// Lift the ban of the user named `toon_name`. Answer with
+  /// [`BAD_REQUEST`][ResponseStatus::BAD_REQUEST] if the user is not banned.
+  fn unban_user(
+    &self,
+    _session: &Session,
+    _request: RequestID,
+    _toon_name: &str,
+  ) -> impl Future<Output = Reply> + Send {
+    unsupported()
+  }
+
+  /// Send `message` to the channel, as an
+  /// [`Emote`][crate::MessageType::Emote].
+  fn send_emote(
+    &self,
+    _session: &Session,
+    _request: RequestID,
+    _message: &str,
+  ) -> impl Future<Output = Reply> + Send {
+    unsupported()
+  }
+
+  /// Remove the user `user_id` from the channel, without banning them. The
+  /// handler should broadcast a [`user_leave`][event::user_leave] event for
+  /// the user.
+  fn kick_user(
+    &self,
+    _session: &Session,
+    _request: RequestID,
+    _user_id: UserID,
+  ) -> impl Future<Output = Reply> + Send {
+    unsupported()
+  }
+
+  /// Make the user `user_id` the moderator of the channel. The handler
+  /// should broadcast a [`user_update`][event::user_update] event with the
+  /// new flags of the user.
+  fn set_moderator(
+    &self,
+    _session: &Session,
+    _request: RequestID,
+    _user_id: UserID,
+  ) -> impl Future<Output = Reply> + Send {
+    unsupported()
+  }
+
+  /// Called when a client connects, before its first request.
+  fn on_open(&self, _session: &Session) {}
+
+  /// Called when the connection of a client is closed.
+  fn on_close(&self, _session: &Session) {}
+
+  /// Called when [`serve`][crate::Server::serve] fails to accept a
+  /// connection, e.g. to log the error. The server keeps running.
+  fn on_accept_error(&self, _error: &io::Error) {}
+}
+
+#[inline]
+fn unsupported() -> impl Future<Output = Reply> + Send {
+  ready(Err(ResponseStatus::BAD_REQUEST))
+}
+
+// =============================================================================
+// Dispatch
+// =============================================================================
+
+/// Answer `request` from `session` with `dispatcher`.
+pub async fn dispatch<D>(
+  dispatcher: &D,
+  session: &Session,
+  request: &RequestPacket,
+) -> ResponsePacket
+where
+  D: Dispatcher,
+{
+  let command: RequestType = request.command();
+
+  let reply: Reply = match session.stage().check(command) {
+    Ok(()) => call(dispatcher, session, request).await,
+    Err(status) => Err(status),
+  };
+
+  match reply {
+    Ok(payload) => {
+      session.set_stage(session.stage().next(command));
+      response::ok_with(request, payload)
+    }
+    Err(status) => response::error(request, status),
+  }
+}
+
+async fn call<D>(dispatcher: &D, session: &Session, request: &RequestPacket) -> Reply
+where
+  D: Dispatcher,
+{
+  let payload: &Payload = request.payload();
+  let id: RequestID = request.request();
+
+  match request.command() {
+    RequestType::Authenticate => {
+      let api_key: &str = string(payload, "api_key")?;
+      dispatcher.authenticate(session, id, api_key).await
+    }
+    RequestType::Connect => dispatcher.connect(session, id).await,
+    RequestType::Disconnect => dispatcher.disconnect(session, id).await,
+    RequestType::SendMessage => {
+      let message: &str = string(payload, "message")?;
+      dispatcher.send_message(session, id, message).await
+    }
+    RequestType::SendWhisper => {
+      let user_id: UserID = user_id(payload)?;
+      let message: &str = string(payload, "message")?;
+      dispatcher.send_whisper(session, id, user_id, message).await
+    }
+    RequestType::BanUser => {
+      let user_id: UserID = user_id(payload)?;
+      dispatcher.ban_user(session, id, user_id).await
+    }
+    RequestType::UnbanUser => {
+      let toon_name: &str = string(payload, "toon_name")?;
+      dispatcher.unban_user(session, id, toon_name).await
+    }
+    RequestType::SendEmote => {
+      let message: &str = string(payload, "message")?;
+      dispatcher.send_emote(session, id, message).await
+    }
+    RequestType::KickUser => {
+      let user_id: UserID = user_id(payload)?;
+      dispatcher.kick_user(session, id, user_id).await
+    }
+    RequestType::SetModerator => {
+      let user_id: UserID = user_id(payload)?;
+      dispatcher.set_moderator(session, id, user_id).await
+    }
+  }
+}
+
+// =============================================================================
+// Misc. Utilities
+// =============================================================================
+
+fn string<'a>(payload: &'a Payload, key: &str) -> Result<&'a str, ResponseStatus> {
+  payload
+    .get(key)
+    .and_then(Value::as_str)
+    .ok_or(ResponseStatus::BAD_REQUEST)
+}
+
+fn user_id(payload: &Payload) -> Result<UserID, ResponseStatus> {
+  payload
+    .get("user_id")
+    .cloned()
+    .and_then(|value| from_value(value).ok())
+    .ok_or(ResponseStatus::BAD_REQUEST)
+}
@@ -0,0 +1,100 @@
+//! Constructors for chat events.
+//!
+//! Events sent in reply to a request (e.g. the [`connect`] event following a
+//! connect request) reuse the ID of the request, other events should use a
+//! fresh ID from the server.
+
+use capi_core::types::UserID;
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::Payload;
+use capi_core::RequestID;
+
+// =============================================================================
+// Message Type
+// =============================================================================
+
+/// The kind of a chat message.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MessageType {
+  Whisper,
+  Channel,
+  ServerInfo,
+  ServerError,
+  Emote,
+}
+
+impl MessageType {
+  /// Returns the name of the message type on the wire.
+  #[inline]
+  pub const fn as_str(&self) -> &'static str {
+    match self {
+      Self::Whisper => "Whisper",
+      Self::Channel => "Channel",
+      Self::ServerInfo => "ServerInfo",
+      Self::ServerError => "ServerError",
+      Self::Emote => "Emote",
+    }
+  }
+}
+
+// =============================================================================
+// Events
+// =============================================================================
+
+/// The client joined `channel`.
+#[inline]
+pub fn connect(request: RequestID, channel: &str) -> EventPacket {
+  EventPacket::new(
+    EventType::Connect,
+    request,
+    Payload::from_kv("channel", channel),
+  )
+}
+
+/// The client left chat.
+#[inline]
+pub fn disconnect(request: RequestID) -> EventPacket {
+  EventPacket::new(EventType::Disconnect, request, Payload::new())
+}
+
+/// A message from `user_id`.
+pub fn message(
+  request: RequestID,
+  user_id: UserID,
+  message: &str,
+  kind: MessageType,
+) -> EventPacket {
+  let mut payload: Payload = Payload::new();
+  payload.insert("user_id", user_id);
+  payload.insert("message", message);
+  payload.insert("type", kind.as_str());
+
+  EventPacket::new(EventType::Message, request, payload)
+}
+
+/// A user joined the channel or their flags changed.
+pub fn user_update<F>(request: RequestID, user_id: UserID, toon_name: &str, flags: F) -> EventPacket
+where
+  F: IntoIterator,
+  F::Item: Into<String>,
+{
+  let flags: Vec<String> = flags.into_iter().map(Into::into).collect();
+
+  let mut payload: Payload = Payload::new();
+  payload.insert("user_id", user_id);
+  payload.insert("toon_name", toon_name);
+  payload.insert("flags", flags);
+
+  EventPacket::new(EventType::UserUpdate, request, payload)
+}
+
+/// A user left the channel.
+#[inline]
+pub fn user_leave(request: RequestID, user_id: UserID) -> EventPacket {
+  EventPacket::new(
+    EventType::UserLeave,
+    request,
+    Payload::from_kv("user_id", user_id),
+  )
+}
@@ -0,0 +1,16 @@
+//! Tools for hosting a Blizzard Classic Chat API (CAPI) compatible server.
+
+pub mod dispatch;
+pub mod event;
+pub mod response;
+pub mod server;
+pub mod session;
+
+pub use self::dispatch::Dispatcher;
+pub use self::dispatch::Reply;
+pub use self::event::MessageType;
+pub use self::server::Server;
+pub use self::session::Closed;
+pub use self::session::Session;
+pub use self::session::SessionID;
+pub use capi_core::Stage;
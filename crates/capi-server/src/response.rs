@@ -0,0 +1,39 @@
+//! Helpers for building responses.
+//!
+//! Successful responses carry no status, failed responses carry the status
+//! reported to the client.
+
+use capi_core::Payload;
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+
+/// Create a successful response to `request` with an empty payload.
+#[inline]
+pub fn ok(request: &RequestPacket) -> ResponsePacket {
+  ok_with(request, Payload::new())
+}
+
+/// Create a successful response to `request` with the given `payload`.
+#[inline]
+pub fn ok_with(request: &RequestPacket, payload: Payload) -> ResponsePacket {
+  ResponsePacket::new(
+    request.command().response(),
+    request.request(),
+    payload,
+    None,
+  )
+}
+
+/// Create a failed response to `request` with the given `status`.
+///
+/// An [`OK`][ResponseStatus::OK] status creates a successful response.
+#[inline]
+pub fn error(request: &RequestPacket, status: ResponseStatus) -> ResponsePacket {
+  ResponsePacket::new(
+    request.command().response(),
+    request.request(),
+    Payload::new(),
+    Some(status).filter(|status| !status.is_ok()),
+  )
+}
@@ -0,0 +1,281 @@
+//! Serving clients over WebSocket.
+
+use capi_core::EventPacket;
+use capi_core::RequestID;
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+use capi_core::Stage;
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use serde_json::from_str;
+use serde_json::to_string;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Error;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::dispatch::dispatch;
+use crate::dispatch::Dispatcher;
+use crate::session::Outgoing;
+use crate::session::Session;
+use crate::session::SessionID;
+
+// =============================================================================
+// Server
+// =============================================================================
+
+struct Shared<D> {
+  dispatcher: D,
+  buffer: usize,
+  sessions: Mutex<BTreeMap<SessionID, Session>>,
+  next_session: AtomicU64,
+  next_event: AtomicU64,
+}
+
+/// A chat API server, answering requests with a [`Dispatcher`].
+///
+/// The server keeps track of connected [`Session`]s. It is cheap to clone,
+/// clones share the dispatcher and sessions.
+///
+/// Connections are served by tokio tasks, the server must be used from within
+/// a tokio runtime. TLS is not handled by the server; [`accept`][Self::accept]
+/// a stream wrapped by a TLS acceptor, or terminate TLS in front of it.
+pub struct Server<D> {
+  shared: Arc<Shared<D>>,
+}
+
+impl<D: Dispatcher> Server<D> {
+  /// The default number of events queued per session.
+  pub const DEFAULT_BUFFER: usize = 1024;
+
+  /// The time to wait before accepting connections again after running out
+  /// of resources (e.g. file descriptors).
+  const ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+  /// Create a new `Server` answering requests with `dispatcher`.
+  #[inline]
+  pub fn new(dispatcher: D) -> Self {
+    Self::with_buffer(dispatcher, Self::DEFAULT_BUFFER)
+  }
+
+  /// Create a new `Server` queueing up to `buffer` events per session.
+  ///
+  /// A session whose client does not keep up with its events is closed once
+  /// the queue is full. A `buffer` of zero is raised to one.
+  pub fn with_buffer(dispatcher: D, buffer: usize) -> Self {
+    Self {
+      shared: Arc::new(Shared {
+        dispatcher,
+        buffer: buffer.max(1),
+        sessions: Mutex::new(BTreeMap::new()),
+        next_session: AtomicU64::new(1),
+        next_event: AtomicU64::new(1),
+      }),
+    }
+  }
+
+  /// Returns a reference to the dispatcher.
+  #[inline]
+  pub fn dispatcher(&self) -> &D {
+    &self.shared.dispatcher
+  }
+
+  /// Returns the session with the given `id`, if it is connected.
+  pub fn session(&self, id: SessionID) -> Option<Session> {
+    self.sessions_lock().get(&id).cloned()
+  }
+
+  /// Returns all connected sessions.
+  pub fn sessions(&self) -> Vec<Session> {
+    self.sessions_lock().values().cloned().collect()
+  }
+
+  /// Returns a new request ID for an event not caused by a request.
+  #[inline]
+  pub fn event_id(&self) -> RequestID {
+    RequestID::new(self.shared.next_event.fetch_add(1, Ordering::Relaxed))
+  }
+
+  /// Send `event` to every session in chat.
+  pub fn broadcast(&self, event: &EventPacket) {
+    for session in self.sessions_lock().values() {
+      if session.stage() == Stage::InChat {
+        let _ignore = session.send(event);
+      }
+    }
+  }
+
+  /// Accept connections from `listener` until it fails.
+  ///
+  /// Errors accepting a single connection are reported to
+  /// [`Dispatcher::on_accept_error`] and do not stop the server. When out of
+  /// resources (e.g. file descriptors), the server pauses briefly before
+  /// accepting again.
+  pub async fn serve(&self, listener: TcpListener) -> io::Result<()> {
+    loop {
+      let (stream, _peer): (TcpStream, SocketAddr) = match listener.accept().await {
+        Ok(connection) => connection,
+        Err(error) if error.kind() == io::ErrorKind::InvalidInput => return Err(error),
+        Err(error) => {
+          self.shared.dispatcher.on_accept_error(&error);
+
+          if !is_connection_error(&error) {
+            tokio::time::sleep(Self::ACCEPT_BACKOFF).await;
+          }
+
+          continue;
+        }
+      };
+
+      let this: Self = self.clone();
+
+      tokio::spawn(async move {
+        // A failed handshake only concerns the client.
+        let _ignore = this.accept(stream).await;
+      });
+    }
+  }
+
+  /// Perform the WebSocket handshake on `stream` and serve the connection
+  /// until it is closed.
+  pub async fn accept<S>(&self, stream: S) -> Result<(), Error>
+  where
+    S: AsyncRead + AsyncWrite + Unpin,
+  {
+    let socket: WebSocketStream<S> = accept_async(stream).await?;
+
+    self.run(socket).await;
+
+    Ok(())
+  }
+
+  /// Serve an established WebSocket connection until it is closed.
+  pub async fn run<S>(&self, mut socket: WebSocketStream<S>)
+  where
+    S: AsyncRead + AsyncWrite + Unpin,
+  {
+    let (send, mut recv): (mpsc::Sender<Outgoing>, _) = mpsc::channel(self.shared.buffer);
+
+    let id: SessionID = SessionID::new(self.shared.next_session.fetch_add(1, Ordering::Relaxed));
+    let session: Session = Session::new(id, send);
+
+    self.sessions_lock().insert(id, session.clone());
+    self.shared.dispatcher.on_open(&session);
+
+    let close: Option<CloseFrame<'static>> = loop {
+      tokio::select! {
+        message = socket.next() => {
+          let data: String = match message {
+            Some(Ok(Message::Text(data))) => data,
+            Some(Ok(Message::Binary(data))) => match String::from_utf8(data) {
+              Ok(data) => data,
+              Err(_) => break Some(invalid("invalid UTF-8")),
+            },
+            Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => continue,
+            Some(Ok(Message::Close(_)) | Err(_)) | None => break None,
+          };
+
+          let Ok(request) = from_str::<RequestPacket>(&data) else {
+            break Some(invalid("malformed request"));
+          };
+
+          let response: ResponsePacket = dispatch(&self.shared.dispatcher, &session, &request).await;
+
+          let Ok(data) = to_string(&response) else {
+            break None;
+          };
+
+          if socket.send(Message::Text(data)).await.is_err() {
+            break None;
+          }
+        }
+        outgoing = recv.recv() => match outgoing {
+          Some(Outgoing::Data(data)) => {
+            if socket.send(Message::Text(data)).await.is_err() {
+              break None;
+            }
+          }
+          Some(Outgoing::Close) | None => break None,
+        },
+        () = session.lagged() => {
+          break Some(CloseFrame {
+            code: CloseCode::Again,
+            reason: "too many queued events".into(),
+          });
+        }
+      }
+    };
+
+    self.sessions_lock().remove(&id);
+    self.shared.dispatcher.on_close(&session);
+
+    // The connection may already be gone.
+    let _ignore = socket.close(close).await;
+  }
+
+  fn sessions_lock(&self) -> MutexGuard<'_, BTreeMap<SessionID, Session>> {
+    self
+      .shared
+      .sessions
+      .lock()
+      .unwrap_or_else(|error| error.into_inner())
+  }
+}
+
+impl<D> Clone for Server<D> {
+  #[inline]
+  fn clone(&self) -> Self {
+    Self {
+      shared: Arc::clone(&self.shared),
+    }
+  }
+}
+
+impl<D: Debug> Debug for Server<D> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Server")
+      .field("dispatcher", &self.shared.dispatcher)
+      .finish_non_exhaustive()
+  }
+}
+
+/// Returns `true` if `error` concerns a single connection, rather than the
+/// listener or the system.
+fn is_connection_error(error: &io::Error) -> bool {
+  matches!(
+    error.kind(),
+    io::ErrorKind::ConnectionAborted
+      | io::ErrorKind::ConnectionRefused
+      | io::ErrorKind::ConnectionReset
+      | io::ErrorKind::Interrupted
+      | io::ErrorKind::TimedOut
+      | io::ErrorKind::WouldBlock
+  )
+}
+
+#[inline]
+fn invalid(reason: &'static str) -> CloseFrame<'static> {
+  CloseFrame {
+    code: CloseCode::Invalid,
+    reason: reason.into(),
+  }
+}
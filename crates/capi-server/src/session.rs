@@ -0,0 +1,179 @@
+//! Connected clients.
+
+use capi_core::EventPacket;
+use capi_core::Stage;
+use serde_json::to_string;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Notify;
+
+// =============================================================================
+// Session ID
+// =============================================================================
+
+/// Identifies a connection for the lifetime of a [`Server`][crate::Server].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SessionID(u64);
+
+impl SessionID {
+  #[inline]
+  pub(crate) const fn new(value: u64) -> Self {
+    Self(value)
+  }
+
+  #[inline]
+  pub const fn get(&self) -> u64 {
+    self.0
+  }
+}
+
+impl Display for SessionID {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    write!(f, "SessionID({})", self.0)
+  }
+}
+
+// =============================================================================
+// Session Error
+// =============================================================================
+
+/// The connection of a [`Session`] is closed.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Closed;
+
+impl Display for Closed {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.write_str("session closed")
+  }
+}
+
+impl Error for Closed {}
+
+// =============================================================================
+// Session
+// =============================================================================
+
+/// A frame queued for the connection of a session.
+#[derive(Debug)]
+pub(crate) enum Outgoing {
+  Data(String),
+  Close,
+}
+
+struct Inner {
+  id: SessionID,
+  stage: Mutex<Stage>,
+  send: Sender<Outgoing>,
+  lagged: Notify,
+}
+
+/// A handle to a connected client.
+///
+/// Handles are cheap to clone and can be kept to send events to the client
+/// at any time, e.g. from a handler of another session.
+#[derive(Clone)]
+pub struct Session {
+  inner: Arc<Inner>,
+}
+
+impl Session {
+  pub(crate) fn new(id: SessionID, send: Sender<Outgoing>) -> Self {
+    Self {
+      inner: Arc::new(Inner {
+        id,
+        stage: Mutex::new(Stage::Unauthenticated),
+        send,
+        lagged: Notify::new(),
+      }),
+    }
+  }
+
+  /// Returns the ID of the session.
+  #[inline]
+  pub fn id(&self) -> SessionID {
+    self.inner.id
+  }
+
+  /// Returns the current stage of the session.
+  #[inline]
+  pub fn stage(&self) -> Stage {
+    *self
+      .inner
+      .stage
+      .lock()
+      .unwrap_or_else(|error| error.into_inner())
+  }
+
+  #[inline]
+  pub(crate) fn set_stage(&self, stage: Stage) {
+    *self
+      .inner
+      .stage
+      .lock()
+      .unwrap_or_else(|error| error.into_inner()) = stage;
+  }
+
+  /// Returns `true` if the connection of the session is closed.
+  #[inline]
+  pub fn is_closed(&self) -> bool {
+    self.inner.send.is_closed()
+  }
+
+  /// Send `event` to the client.
+  ///
+  /// Events are queued, if the client falls too far behind the session is
+  /// closed and [`Closed`] returned.
+  pub fn send(&self, event: &EventPacket) -> Result<(), Closed> {
+    // Serializing an event cannot fail, its payload is a map with string keys.
+    let data: String = to_string(event).map_err(|_| Closed)?;
+
+    self.send_data(data)
+  }
+
+  /// Close the connection of the session.
+  ///
+  /// Frames queued before are still sent.
+  #[inline]
+  pub fn close(&self) {
+    let _ignore = self.push(Outgoing::Close);
+  }
+
+  #[inline]
+  pub(crate) fn send_data(&self, data: String) -> Result<(), Closed> {
+    self.push(Outgoing::Data(data))
+  }
+
+  /// Waits until the session is closed for falling behind.
+  #[inline]
+  pub(crate) async fn lagged(&self) {
+    self.inner.lagged.notified().await;
+  }
+
+  fn push(&self, outgoing: Outgoing) -> Result<(), Closed> {
+    match self.inner.send.try_send(outgoing) {
+      Ok(()) => Ok(()),
+      Err(TrySendError::Full(_)) => {
+        // Stored until the connection task gets to it.
+        self.inner.lagged.notify_one();
+        Err(Closed)
+      }
+      Err(TrySendError::Closed(_)) => Err(Closed),
+    }
+  }
+}
+
+impl Debug for Session {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Session")
+      .field("id", &self.id())
+      .field("stage", &self.stage())
+      .finish()
+  }
+}
@@ -0,0 +1,280 @@
+//! Serving sessions over an in-memory connection.
+
+use capi_core::packet::Authenticate;
+use capi_core::packet::ChatConnect;
+use capi_core::packet::ChatSendMessage;
+use capi_core::types::UserID;
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::Payload;
+use capi_core::RequestID;
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use capi_core::Stage;
+use capi_server::event;
+use capi_server::Dispatcher;
+use capi_server::MessageType;
+use capi_server::Reply;
+use capi_server::Server;
+use capi_server::Session;
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use serde_json::from_str;
+use serde_json::to_string;
+use serde_json::Value;
+use tokio::io::DuplexStream;
+use tokio_tungstenite::client_async;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+const API_KEY: &str = "api-key";
+const CHANNEL: &str = "Op Test";
+
+struct Chat;
+
+impl Dispatcher for Chat {
+  async fn authenticate(&self, _session: &Session, _request: RequestID, api_key: &str) -> Reply {
+    if api_key == API_KEY {
+      Ok(Payload::new())
+    } else {
+      Err(ResponseStatus::BAD_REQUEST)
+    }
+  }
+
+  async fn connect(&self, session: &Session, request: RequestID) -> Reply {
+    let _ignore = session.send(&event::connect(request, CHANNEL));
+    Ok(Payload::new())
+  }
+
+  async fn send_message(&self, _session: &Session, _request: RequestID, _message: &str) -> Reply {
+    Ok(Payload::new())
+  }
+}
+
+/// A client connected to a server task.
+struct Client {
+  server: Server<Chat>,
+  socket: WebSocketStream<DuplexStream>,
+}
+
+impl Client {
+  async fn connect() -> Self {
+    Self::connect_to(&Server::new(Chat)).await
+  }
+
+  async fn connect_to(server: &Server<Chat>) -> Self {
+    let (local, remote): (DuplexStream, DuplexStream) = tokio::io::duplex(4096);
+    let this: Server<Chat> = server.clone();
+
+    tokio::spawn(async move { this.accept(remote).await });
+
+    let (socket, _response) = client_async("ws://localhost/", local).await.unwrap();
+
+    Self {
+      server: server.clone(),
+      socket,
+    }
+  }
+
+  /// Authenticate and enter chat, skipping the connect event.
+  async fn enter_chat(&mut self) {
+    assert_eq!(
+      self
+        .send(RequestPacket::new(Authenticate { api_key: API_KEY }))
+        .await,
+      None
+    );
+
+    assert_eq!(self.send(RequestPacket::new(ChatConnect)).await, None);
+
+    self.recv().await;
+  }
+
+  /// Skip data frames until the server closes the connection.
+  async fn closed(&mut self) -> Option<CloseFrame<'static>> {
+    loop {
+      match self.socket.next().await {
+        Some(Ok(Message::Text(_))) => continue,
+        Some(Ok(Message::Close(frame))) => return frame,
+        message => panic!("unexpected message: {message:?}"),
+      }
+    }
+  }
+
+  async fn recv(&mut self) -> String {
+    match self.socket.next().await {
+      Some(Ok(Message::Text(data))) => data,
+      message => panic!("unexpected message: {message:?}"),
+    }
+  }
+
+  /// Send `request` and return the status of its response.
+  async fn send(&mut self, request: RequestPacket) -> Option<ResponseStatus> {
+    let data: String = to_string(&request).unwrap();
+
+    self.socket.send(Message::Text(data)).await.unwrap();
+
+    let response: ResponsePacket = from_str(&self.recv().await).unwrap();
+
+    assert_eq!(response.request(), request.request());
+
+    response.status()
+  }
+
+  fn stage(&self) -> Stage {
+    self.server.sessions()[0].stage()
+  }
+}
+
+#[tokio::test]
+async fn requests_out_of_order_are_rejected() {
+  let mut client: Client = Client::connect().await;
+
+  assert_eq!(
+    client.send(RequestPacket::new(ChatConnect)).await,
+    Some(ResponseStatus::BAD_REQUEST)
+  );
+
+  assert_eq!(
+    client
+      .send(RequestPacket::new(Authenticate { api_key: API_KEY }))
+      .await,
+    None
+  );
+
+  assert_eq!(
+    client
+      .send(RequestPacket::new(ChatSendMessage { message: "hello" }))
+      .await,
+    Some(ResponseStatus::NOT_CONNECTED)
+  );
+
+  assert_eq!(
+    client
+      .send(RequestPacket::new(Authenticate { api_key: API_KEY }))
+      .await,
+    Some(ResponseStatus::BAD_REQUEST)
+  );
+
+  assert_eq!(client.stage(), Stage::Authenticated);
+}
+
+#[tokio::test]
+async fn session_enters_chat() {
+  let mut client: Client = Client::connect().await;
+
+  assert_eq!(client.stage(), Stage::Unauthenticated);
+
+  assert_eq!(
+    client
+      .send(RequestPacket::new(Authenticate { api_key: API_KEY }))
+      .await,
+    None
+  );
+
+  assert_eq!(client.send(RequestPacket::new(ChatConnect)).await, None);
+
+  let event: EventPacket = from_str(&client.recv().await).unwrap();
+
+  assert_eq!(event.command(), EventType::Connect);
+  assert_eq!(
+    event.payload().get("channel").and_then(Value::as_str),
+    Some(CHANNEL)
+  );
+  assert_eq!(client.stage(), Stage::InChat);
+
+  assert_eq!(
+    client
+      .send(RequestPacket::new(ChatSendMessage { message: "hello" }))
+      .await,
+    None
+  );
+}
+
+#[tokio::test]
+async fn broadcast_reaches_only_sessions_in_chat() {
+  let server: Server<Chat> = Server::new(Chat);
+  let mut chatting: Client = Client::connect_to(&server).await;
+  let mut waiting: Client = Client::connect_to(&server).await;
+
+  chatting.enter_chat().await;
+
+  assert_eq!(
+    waiting
+      .send(RequestPacket::new(Authenticate { api_key: API_KEY }))
+      .await,
+    None
+  );
+
+  let message: EventPacket = event::message(
+    server.event_id(),
+    UserID::new(1),
+    "hi",
+    MessageType::Channel,
+  );
+
+  server.broadcast(&message);
+
+  let event: EventPacket = from_str(&chatting.recv().await).unwrap();
+
+  assert_eq!(event.command(), EventType::Message);
+
+  // The next frame the other session receives is the response, not the event.
+  assert_eq!(
+    waiting
+      .send(RequestPacket::new(ChatSendMessage { message: "hello" }))
+      .await,
+    Some(ResponseStatus::NOT_CONNECTED)
+  );
+}
+
+#[tokio::test]
+async fn closing_a_session_ends_its_connection() {
+  let mut client: Client = Client::connect().await;
+
+  client.enter_chat().await;
+  client.server.sessions()[0].close();
+
+  assert_eq!(client.closed().await, None);
+  assert!(client.server.sessions().is_empty());
+}
+
+#[tokio::test]
+async fn malformed_request_closes_the_connection() {
+  let mut client: Client = Client::connect().await;
+
+  client
+    .socket
+    .send(Message::Text("not a request".to_owned()))
+    .await
+    .unwrap();
+
+  let frame: CloseFrame<'static> = client.closed().await.unwrap();
+
+  assert_eq!(frame.code, CloseCode::Invalid);
+}
+
+#[tokio::test]
+async fn lagging_session_is_closed() {
+  let server: Server<Chat> = Server::with_buffer(Chat, 4);
+  let mut client: Client = Client::connect_to(&server).await;
+
+  client.enter_chat().await;
+
+  // The connection task cannot drain the queue in between.
+  for _ in 0..16 {
+    server.broadcast(&event::message(
+      server.event_id(),
+      UserID::new(1),
+      "spam",
+      MessageType::Channel,
+    ));
+  }
+
+  let frame: CloseFrame<'static> = client.closed().await.unwrap();
+
+  assert_eq!(frame.code, CloseCode::Again);
+}
@@ -0,0 +1,325 @@
+//! A synchronous chat API client.
+//!
+//! The [`Client`] runs a [`Channel`] on an internal runtime thread and blocks
+//! the calling thread for each operation, for programs that do not otherwise
+//! need an async runtime.
+//!
+//! ```no_run
+//! use capi_socket::blocking::Client;
+//!
+//! let client: Client = Client::connect()?;
+//!
+//! client.send_authenticate("api-key")?;
+//! client.send_connect()?;
+//! client.send_message("Hello")?;
+//! client.close()?;
+//! # Ok::<(), capi_socket::channel::ChannelError>(())
+//! ```
+//!
+//! # Panics
+//!
+//! Blocking operations panic if called from within an async runtime.
+
+use capi_core::packet::Authenticate;
+use capi_core::packet::ChatBanUser;
+use capi_core::packet::ChatConnect;
+use capi_core::packet::ChatDisconnect;
+use capi_core::packet::ChatKickUser;
+use capi_core::packet::ChatSendEmote;
+use capi_core::packet::ChatSendMessage;
+use capi_core::packet::ChatSendWhisper;
+use capi_core::packet::ChatSetModerator;
+use capi_core::packet::ChatUnbanUser;
+use capi_core::types::UserID;
+use capi_core::EventPacket;
+use capi_core::IntoPayload;
+use capi_core::Packet;
+use capi_core::ResponsePacket;
+use futures_util::StreamExt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Builder;
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+
+use crate::channel::runtime;
+use crate::channel::Channel;
+use crate::channel::ChannelError;
+use crate::channel::ErrorKind;
+use crate::channel::Shutdown;
+use crate::channel::ShutdownSummary;
+use crate::socket::Socket as _;
+use crate::transport::tungstenite::connect_with_config;
+use crate::transport::tungstenite::Config;
+use crate::transport::tungstenite::Socket;
+
+// =============================================================================
+// Runtime
+// =============================================================================
+
+/// A single-threaded tokio runtime on a background thread.
+///
+/// The thread drives the connection while the caller is not blocked on it.
+struct Runtime {
+  handle: Handle,
+  stop: Option<oneshot::Sender<()>>,
+  thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Runtime {
+  fn new() -> Result<Self, ChannelError> {
+    let runtime: tokio::runtime::Runtime = Builder::new_current_thread()
+      .enable_io()
+      .build()
+      .map_err(|error| ChannelError::new(ErrorKind::Socket, error))?;
+
+    let handle: Handle = runtime.handle().clone();
+    let (stop, wait): (oneshot::Sender<()>, oneshot::Receiver<()>) = oneshot::channel();
+
+    let thread: thread::JoinHandle<()> = thread::Builder::new()
+      .name("capi-blocking".to_owned())
+      .spawn(move || {
+        // Either a stop signal or a dropped sender ends the runtime.
+        let _ignore = runtime.block_on(wait);
+      })
+      .map_err(|error| ChannelError::new(ErrorKind::Socket, error))?;
+
+    Ok(Self {
+      handle,
+      stop: Some(stop),
+      thread: Some(thread),
+    })
+  }
+
+  #[inline]
+  fn block_on<F: Future>(&self, future: F) -> F::Output {
+    self.handle.block_on(future)
+  }
+}
+
+impl Drop for Runtime {
+  fn drop(&mut self) {
+    if let Some(stop) = self.stop.take() {
+      let _ignore = stop.send(());
+    }
+
+    if let Some(thread) = self.thread.take() {
+      let _ignore = thread.join();
+    }
+  }
+}
+
+// =============================================================================
+// Client
+// =============================================================================
+
+/// A blocking chat API client.
+///
+/// Offers the operations of [`SocketExt`][crate::SocketExt] as blocking
+/// methods. Dropping the client closes the connection without waiting for
+/// pending requests, see [`close`][Self::close].
+pub struct Client {
+  // Declared first, the channel must be dropped before the runtime.
+  channel: Channel<Socket>,
+  runtime: Runtime,
+}
+
+impl Client {
+  /// Connect to the chat API endpoint.
+  #[inline]
+  pub fn connect() -> Result<Self, ChannelError> {
+    Self::connect_with_config(Config::new())
+  }
+
+  /// Connect with the given `config`.
+  pub fn connect_with_config(config: Config) -> Result<Self, ChannelError> {
+    let runtime: Runtime = Runtime::new()?;
+    let channel: Channel<Socket> = runtime.block_on(connect_with_config(config))?;
+
+    Ok(Self { channel, runtime })
+  }
+
+  /// Returns a reference to the underlying channel.
+  #[inline]
+  pub const fn channel(&self) -> &Channel<Socket> {
+    &self.channel
+  }
+
+  /// Returns `true` if the connection has stopped.
+  #[inline]
+  pub fn is_finished(&self) -> bool {
+    self.channel.task().is_finished()
+  }
+
+  /// Send a request and wait for the response.
+  pub fn send<P>(&self, payload: P) -> Result<ResponsePacket, ChannelError>
+  where
+    P: Packet + IntoPayload,
+  {
+    self.runtime.block_on(self.channel.send(payload))
+  }
+
+  /// Send a request, waiting at most `timeout` for the response.
+  pub fn send_with_timeout<P>(&self, payload: P, timeout: Duration) -> Result<ResponsePacket, ChannelError>
+  where
+    P: Packet + IntoPayload,
+  {
+    self.runtime.block_on(self.channel.send_with_timeout(payload, timeout))
+  }
+
+  /// Send an authentication request with the API key.
+  #[inline]
+  pub fn send_authenticate(&self, api_key: &str) -> Result<ResponsePacket, ChannelError> {
+    self.send(Authenticate { api_key })
+  }
+
+  /// Connect the bot to the gateway and chat channel.
+  #[inline]
+  pub fn send_connect(&self) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatConnect)
+  }
+
+  /// Disconnects the bot from the gateway and chat channel.
+  #[inline]
+  pub fn send_disconnect(&self) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatDisconnect)
+  }
+
+  /// Sends a chat message to the channel.
+  #[inline]
+  pub fn send_message(&self, message: &str) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatSendMessage { message })
+  }
+
+  /// Sends a chat message to one user in the channel.
+  #[inline]
+  pub fn send_whisper(&self, message: &str, user_id: UserID) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatSendWhisper { message, user_id })
+  }
+
+  /// Bans a user from the channel.
+  #[inline]
+  pub fn ban_user(&self, user_id: UserID) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatBanUser { user_id })
+  }
+
+  /// Un-Bans a user from the channel.
+  #[inline]
+  pub fn unban_user(&self, toon_name: &str) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatUnbanUser { toon_name })
+  }
+
+  /// Sends an emote on behalf of a bot.
+  #[inline]
+  pub fn send_emote(&self, message: &str) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatSendEmote { message })
+  }
+
+  /// Kicks a user from the channel.
+  #[inline]
+  pub fn kick_user(&self, user_id: UserID) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatKickUser { user_id })
+  }
+
+  /// Sets the current chat moderator to a member of the current chat.
+  #[inline]
+  pub fn set_moderator(&self, user_id: UserID) -> Result<ResponsePacket, ChannelError> {
+    self.send(ChatSetModerator { user_id })
+  }
+
+  /// Wait for the next event.
+  ///
+  /// Returns `None` once the connection has stopped and all events were
+  /// received.
+  pub fn recv(&mut self) -> Option<Result<EventPacket, ChannelError>> {
+    self.runtime.block_on(self.channel.event_stream().next())
+  }
+
+  /// Wait at most `timeout` for the next event.
+  ///
+  /// Fails with [`ErrorKind::Timeout`] if no event arrived in time.
+  pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Result<EventPacket, ChannelError>> {
+    let Self { channel, runtime } = self;
+
+    match runtime.block_on(runtime::timeout(timeout, channel.event_stream().next())) {
+      Ok(event) => event,
+      Err(error) => Some(Err(ChannelError::new(ErrorKind::Timeout, error))),
+    }
+  }
+
+  /// Returns a blocking iterator over events.
+  ///
+  /// The iterator ends once the connection has stopped.
+  #[inline]
+  pub fn events(&mut self) -> Events<'_> {
+    Events {
+      client: self,
+      timeout: None,
+    }
+  }
+
+  /// Returns a blocking iterator over events, ending once no event arrived
+  /// for `timeout`.
+  #[inline]
+  pub fn events_timeout(&mut self, timeout: Duration) -> Events<'_> {
+    Events {
+      client: self,
+      timeout: Some(timeout),
+    }
+  }
+
+  /// Gracefully stop the connection, see [`Shutdown`].
+  pub fn shutdown(&self, options: Shutdown) -> Result<ShutdownSummary, ChannelError> {
+    self.runtime.block_on(self.channel.shutdown(options))
+  }
+
+  /// Gracefully stop the connection and wait for it to close.
+  pub fn close(self) -> Result<(), ChannelError> {
+    let Self { channel, runtime } = self;
+
+    runtime.block_on(async move {
+      channel.stop().await?;
+      channel.join().await
+    })
+  }
+}
+
+impl Debug for Client {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Client")
+      .field("channel", &self.channel)
+      .finish_non_exhaustive()
+  }
+}
+
+// =============================================================================
+// Events
+// =============================================================================
+
+/// A blocking iterator over the events of a [`Client`].
+///
+/// Created by [`Client::events`] and [`Client::events_timeout`].
+#[derive(Debug)]
+pub struct Events<'a> {
+  client: &'a mut Client,
+  timeout: Option<Duration>,
+}
+
+impl Iterator for Events<'_> {
+  type Item = Result<EventPacket, ChannelError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let Some(timeout) = self.timeout else {
+      return self.client.recv();
+    };
+
+    match self.client.recv_timeout(timeout) {
+      Some(Err(error)) if error.kind() == ErrorKind::Timeout => None,
+      event => event,
+    }
+  }
+}
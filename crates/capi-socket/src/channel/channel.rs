@@ -1,9 +1,14 @@
+use capi_core::packet::ChatDisconnect;
 use capi_core::EventPacket;
+use capi_core::EventType;
 use capi_core::IntoPayload;
 use capi_core::Packet;
 use capi_core::RequestID;
 use capi_core::RequestPacket;
+use capi_core::RequestType;
 use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use capi_core::ResponseType;
 use futures_util::stream::SplitSink;
 use futures_util::stream::SplitStream;
 use futures_util::SinkExt;
@@ -14,20 +19,64 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_json::from_str;
 use serde_json::to_string;
+use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
+use std::collections::VecDeque;
 use std::error::Error;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::future::Future;
 use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::error::TryRecvError;
 use tokio::sync::oneshot;
-use tokio::task;
-use tokio::task::JoinHandle;
-use tokio_stream::wrappers::ReceiverStream;
+use tokio::sync::Notify;
 
+use crate::channel::ChannelConfig;
 use crate::channel::ChannelError;
+use crate::channel::Control;
 use crate::channel::ErrorKind;
+use crate::channel::JoinHandle;
+use crate::channel::heartbeat::Beat;
+use crate::channel::heartbeat::Latency;
+use crate::channel::heartbeat::Pulse;
+use crate::channel::limiter::Limiter;
+use crate::channel::metrics::Metrics;
+use crate::channel::Frame;
+use crate::channel::LocalTransport;
 use crate::channel::Transport;
 use crate::channel::Message;
+use crate::channel::Filter;
+use crate::channel::Overflow;
+use crate::channel::Priority;
+use crate::channel::Shutdown;
+use crate::channel::ShutdownSummary;
+use crate::channel::Spawner;
+use crate::channel::Subscription;
+#[cfg(feature = "tokio")]
+use crate::channel::TokioSpawner;
+use crate::channel::subscription::FanoutRecv;
+use crate::channel::subscription::FanoutSend;
+#[cfg(feature = "tracing")]
+use crate::channel::trace::Redacted;
+use crate::channel::trace::RequestSpan;
+use crate::channel::queue::queue;
+use crate::channel::queue::QueueRecv;
+use crate::channel::queue::QueueSend;
+use crate::channel::runtime;
+use crate::middleware::Handler;
 use crate::socket::Socket;
 use crate::socket::SocketResponse;
 
@@ -35,14 +84,24 @@ use crate::socket::SocketResponse;
 // Misc. Types
 // =============================================================================
 
-type ClientRecv<T> = mpsc::Receiver<Command<T>>;
-type ClientSend<T> = mpsc::Sender<Command<T>>;
+type ClientRecv<T> = [mpsc::Receiver<Command<T>>; Priority::COUNT];
+type ClientSend<T> = [mpsc::Sender<Command<T>>; Priority::COUNT];
 
-type ServerRecv<T> = ReceiverStream<T>;
-type ServerSend<T> = mpsc::Sender<T>;
+type Permit<T> = mpsc::OwnedPermit<Command<T>>;
 
-type SoloRecv<T> = oneshot::Receiver<T>;
-type SoloSend<T> = oneshot::Sender<T>;
+type CancelRecv = mpsc::UnboundedReceiver<RequestID>;
+type CancelSend = mpsc::UnboundedSender<RequestID>;
+
+type ServerRecv<T> = QueueRecv<Reply<T>>;
+type ServerSend<T> = QueueSend<Reply<T>>;
+
+// Responses come with the time spent waiting for the limiter
+type SoloRecv<T> = oneshot::Receiver<Reply<(T, Duration)>>;
+type SoloSend<T> = oneshot::Sender<Reply<(T, Duration)>>;
+
+type Reply<T> = Result<T, ChannelError>;
+
+static ERR_STOPPING: &str = "channel is shutting down";
 
 // =============================================================================
 // Channel Command
@@ -50,8 +109,7 @@ type SoloSend<T> = oneshot::Sender<T>;
 
 enum Command<T> {
   SendRequest(Request<T>),
-  ShutdownExit,
-  ShutdownKill,
+  ShutdownExit(Shutdown, oneshot::Sender<ShutdownSummary>),
 }
 
 // =============================================================================
@@ -61,97 +119,1033 @@ enum Command<T> {
 struct Request<T> {
   message: T,
   request: RequestID,
+  command: RequestType,
   oneshot: SoloSend<T>,
 }
 
+/// A request picked by the run loop that is waiting for the [`Limiter`].
+struct Throttled<T> {
+  message: T,
+  request: RequestID,
+  command: RequestType,
+  picked: Instant,
+  since: Instant,
+  delay: Duration,
+}
+
+impl<T> Throttled<T> {
+  #[inline]
+  fn new(message: T, request: RequestID, command: RequestType, delay: Duration) -> Self {
+    let now: Instant = Instant::now();
+
+    Self {
+      message,
+      request,
+      command,
+      picked: now,
+      since: now,
+      delay,
+    }
+  }
+
+  /// Time left until the limiter has a token.
+  #[inline]
+  fn remaining(&self) -> Duration {
+    self.delay.saturating_sub(self.since.elapsed())
+  }
+}
+
 // =============================================================================
-// Command Response (ID)
+// Pending Request
+// =============================================================================
+
+/// Deregisters a request from the [`MailBox`] unless disarmed.
+///
+/// Dropping the future returned by [`Channel::send`] drops this guard as well,
+/// which keeps the mailbox from holding on to requests nobody is waiting for.
+struct Pending<'a> {
+  cancel: &'a CancelSend,
+  request: RequestID,
+  armed: bool,
+}
+
+impl<'a> Pending<'a> {
+  #[inline]
+  const fn new(cancel: &'a CancelSend, request: RequestID) -> Self {
+    Self {
+      cancel,
+      request,
+      armed: true,
+    }
+  }
+
+  #[inline]
+  fn disarm(&mut self) {
+    self.armed = false;
+  }
+}
+
+impl Drop for Pending<'_> {
+  fn drop(&mut self) {
+    if self.armed {
+      // The run loop may have exited, nothing left to clean up then.
+      let _ignore = self.cancel.send(self.request);
+    }
+  }
+}
+
+// =============================================================================
+// Command Response (Header)
 // =============================================================================
 
 #[derive(Deserialize)]
 struct Response {
+  command: ResponseKind,
   request_id: RequestID,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ResponseKind {
+  Response(ResponseType),
+  Event(EventType),
+}
+
 // =============================================================================
 // Request/Response MailBox
 // =============================================================================
 
+/// A caller awaiting a response.
+struct Tracked<T> {
+  oneshot: SoloSend<T>,
+  throttled: Duration,
+}
+
 struct MailBox<T> {
   channel: ServerSend<T>,
-  tracker: BTreeMap<RequestID, SoloSend<T>>,
+  metrics: Metrics,
+  delivered: usize,
+  rejected: usize,
+  overflow: Overflow,
+  backlog: VecDeque<Reply<T>>,
+  fanout: Arc<FanoutSend>,
+  tracker: BTreeMap<RequestID, Tracked<T>>,
 }
 
-impl<T> MailBox<T> {
+impl<T: Message> MailBox<T> {
   #[inline]
-  fn new(channel: ServerSend<T>) -> Self {
+  fn new(
+    channel: ServerSend<T>,
+    metrics: Metrics,
+    overflow: Overflow,
+    fanout: Arc<FanoutSend>,
+  ) -> Self {
     Self {
       channel,
+      metrics,
+      delivered: 0,
+      rejected: 0,
+      overflow,
+      backlog: VecDeque::new(),
+      fanout,
       tracker: BTreeMap::new(),
     }
   }
 
-  fn track(&mut self, request: RequestID, oneshot: SoloSend<T>) {
-    assert!(!self.tracker.contains_key(&request));
-    self.tracker.insert(request, oneshot);
-  }
+  /// Move as much of the event backlog as fits into the event queue.
+  fn advance(&mut self) {
+    while let Some(message) = self.backlog.pop_front() {
+      if let Err(message) = self.channel.try_push(message) {
+        self.backlog.push_front(message);
+        break;
+      }
+    }
+  }
+
+  /// Waits until the event backlog is delivered.
+  async fn drain(&mut self) {
+    while !self.backlog.is_empty() {
+      self.channel.ready().await;
+      self.advance();
+    }
+  }
+
+  /// Start tracking the response channel of `request`.
+  ///
+  /// Returns `false` and notifies the caller if `request` is already tracked.
+  /// Returns `false` if the caller is no longer waiting for a response.
+  fn track(&mut self, request: RequestID, oneshot: SoloSend<T>) -> bool {
+    if oneshot.is_closed() {
+      return false;
+    }
+
+    match self.tracker.entry(request) {
+      Entry::Vacant(entry) => {
+        entry.insert(Tracked {
+          oneshot,
+          throttled: Duration::ZERO,
+        });
+        true
+      }
+      Entry::Occupied(_) => {
+        static ERR: &str = "request id is already awaiting a response";
+
+        // The caller may have gone away, there is nobody else to tell.
+        let _ignore = oneshot.send(Err(ChannelError::msg(ErrorKind::DuplicateRequest, ERR)));
+
+        false
+      }
+    }
+  }
+
+  /// Returns `true` if a caller is waiting for `request`.
+  #[inline]
+  fn is_tracked(&self, request: RequestID) -> bool {
+    self.tracker.contains_key(&request)
+  }
+
+  /// Record the time `request` spent waiting for the limiter.
+  #[inline]
+  fn throttled(&mut self, request: RequestID, throttled: Duration) {
+    if let Some(tracked) = self.tracker.get_mut(&request) {
+      tracked.throttled = throttled;
+    }
+  }
+
+  /// Stop tracking `request`, any late response will be discarded.
+  fn forget(&mut self, request: RequestID) {
+    self.tracker.remove(&request);
+  }
+
+  /// Stop tracking `request` and send `error` to the caller.
+  fn reject(&mut self, request: RequestID, error: ChannelError) {
+    if let Some(tracked) = self.tracker.remove(&request) {
+      let _ignore = tracked.oneshot.send(Err(error));
+      self.rejected += 1;
+    }
+  }
+
+  /// Stop tracking all requests and send `error` to every caller.
+  ///
+  /// Returns the number of rejected requests.
+  fn reject_all(&mut self, error: &ChannelError) -> usize {
+    let tracker: BTreeMap<RequestID, Tracked<T>> = mem::take(&mut self.tracker);
+    let count: usize = tracker.len();
+
+    for (_, tracked) in tracker {
+      let _ignore = tracked.oneshot.send(Err(error.replicate()));
+    }
+
+    self.rejected += count;
+
+    count
+  }
+
+  /// Returns `true` if no request is awaiting a response.
+  #[inline]
+  fn is_idle(&self) -> bool {
+    self.tracker.is_empty()
+  }
+
+  fn send(&mut self, response: Response, text: String) -> Result<(), ChannelError> {
+    match response.command {
+      ResponseKind::Response(_) => {
+        // Responses to unknown (or expired) requests are discarded
+        if let Some(tracked) = self.tracker.remove(&response.request_id) {
+          // The caller may have dropped the request before it was deregistered.
+          let _ignore = tracked.oneshot.send(Ok((T::from_string(text), tracked.throttled)));
+          self.delivered += 1;
+        } else {
+          debug!(request_id = ?response.request_id, "discarding response to unknown request");
+        }
+
+        Ok(())
+      }
+      ResponseKind::Event(event) => {
+        debug!(?event, "event received");
+
+        self.metrics.event(event);
+
+        // Only decode the event if someone is subscribed
+        if self.fanout.receiver_count() != 0 {
+          let _ignore = self.fanout.send(Arc::new(decode(text.as_str())));
+        }
+
+        self.publish(Ok(T::from_string(text)))
+      }
+    }
+  }
+
+  fn publish(&mut self, message: Reply<T>) -> Result<(), ChannelError> {
+    if let Err(ref error) = message {
+      self.metrics.error(error);
+
+      if self.fanout.receiver_count() != 0 {
+        let _ignore = self.fanout.send(Arc::new(Err(error.replicate())));
+      }
+    }
+
+    // The receiving half of the channel may be gone while subscribers are
+    // still listening, the queue drops the message in that case.
+    match self.overflow {
+      Overflow::Block if !self.backlog.is_empty() => {
+        self.backlog.push_back(message);
+      }
+      Overflow::Block => {
+        if let Err(message) = self.channel.try_push(message) {
+          self.backlog.push_back(message);
+        }
+      }
+      Overflow::DropOldest => {
+        self.channel.force_push(message);
+      }
+      Overflow::DropNewest => {
+        if self.channel.try_push(message).is_err() {
+          trace!("event stream is full, dropping event");
+        }
+      }
+      Overflow::Error => {
+        if self.channel.try_push(message).is_err() {
+          static ERR: &str = "event stream is full";
+          return Err(ChannelError::msg(ErrorKind::Overflow, ERR));
+        }
+      }
+    }
+
+    self.metrics.event_queue(self.channel.len());
+
+    Ok(())
+  }
+}
+
+// =============================================================================
+// Graceful Shutdown
+// =============================================================================
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stage {
+  /// Waiting for outstanding responses.
+  Draining,
+  /// Waiting for the bot to leave chat.
+  Disconnecting(RequestID),
+  /// Ready to close the connection.
+  Closing,
+}
+
+struct Closing {
+  options: Shutdown,
+  reply: oneshot::Sender<ShutdownSummary>,
+  stage: Stage,
+  deadline: Option<Instant>,
+  delivered: usize,
+  rejected: usize,
+  summary: ShutdownSummary,
+}
+
+impl Closing {
+  fn new<T>(options: Shutdown, reply: oneshot::Sender<ShutdownSummary>, mailbox: &MailBox<T>) -> Self {
+    Self {
+      options,
+      reply,
+      stage: Stage::Draining,
+      deadline: Instant::now().checked_add(options.deadline),
+      delivered: mailbox.delivered,
+      rejected: mailbox.rejected,
+      summary: ShutdownSummary::default(),
+    }
+  }
+
+  /// Returns the time left in the current step, `None` if it has no deadline.
+  #[inline]
+  fn remaining(&self) -> Option<Duration> {
+    self
+      .deadline
+      .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+  }
+
+  #[inline]
+  fn expired(&self) -> bool {
+    self.remaining().is_some_and(|remaining| remaining.is_zero())
+  }
+
+  /// Start waiting for the bot to leave chat after sending `request`.
+  fn disconnecting(&mut self, request: RequestID) {
+    self.stage = Stage::Disconnecting(request);
+    self.deadline = Instant::now().checked_add(self.options.deadline);
+  }
+
+  /// Watch for the end of the disconnect step.
+  fn observe(&mut self, response: &Response, text: &str) {
+    let Stage::Disconnecting(request) = self.stage else {
+      return;
+    };
+
+    match response.command {
+      ResponseKind::Event(EventType::Disconnect) => {
+        self.summary.disconnected = true;
+        self.stage = Stage::Closing;
+      }
+      // The event won't follow if the request failed (e.g. not in chat)
+      ResponseKind::Response(_) if response.request_id == request => {
+        let failed: bool = decode::<ResponsePacket>(text)
+          .map_or(true, |packet| packet.status().is_some_and(|status| !status.is_ok()));
+
+        if failed {
+          self.stage = Stage::Closing;
+        }
+      }
+      ResponseKind::Response(_) | ResponseKind::Event(_) => {}
+    }
+  }
+
+  /// Reject the remaining requests and report the outcome.
+  ///
+  /// Requests rejected because the connection failed count as abandoned.
+  fn finish<T: Message>(mut self, mailbox: &mut MailBox<T>) {
+    static ERR: &str = "channel was shut down";
+
+    let error: ChannelError = ChannelError::msg(ErrorKind::Closed, ERR);
+
+    mailbox.reject_all(&error);
+
+    self.summary.abandoned += mailbox.rejected - self.rejected;
+    self.summary.completed = mailbox.delivered - self.delivered;
+
+    // The caller may have stopped waiting.
+    let _ignore = self.reply.send(self.summary);
+  }
+}
+
+// =============================================================================
+// Reserved Queue Slot
+// =============================================================================
+
+/// A slot in a request queue, reserved ahead of sending a request.
+#[cfg_attr(not(feature = "tower"), allow(dead_code))]
+pub(crate) struct Reserved<T: LocalTransport> {
+  priority: Priority,
+  permit: Permit<T::Message>,
+}
+
+// =============================================================================
+// Channel Handle
+// =============================================================================
+
+/// The sending half of a [`Channel`].
+///
+/// Handles are cheap to clone and can be shared between tasks.
+pub struct ChannelHandle<T: LocalTransport> {
+  send: ClientSend<T::Message>,
+  cancel: CancelSend,
+  config: ChannelConfig,
+  limiter: Arc<Limiter>,
+  latency: Arc<Latency>,
+  stopping: Arc<AtomicBool>,
+  killed: Arc<Notify>,
+  fanout: Weak<FanoutSend>,
+  priority: Option<Priority>,
+  phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: LocalTransport> ChannelHandle<T> {
+  async fn push(&self, command: Command<T::Message>, priority: Priority) -> Result<(), ChannelError> {
+    let queue: &mpsc::Sender<Command<T::Message>> = &self.send[priority.index()];
+
+    queue
+      .send(command)
+      .await
+      .map_err(|error| ChannelError::msg(ErrorKind::ChannSend, error))?;
+
+    self
+      .config
+      .metrics
+      .request_queue(priority, queue.max_capacity() - queue.capacity());
+
+    Ok(())
+  }
+
+  /// Queue `command` in the slot held by `permit`.
+  fn push_reserved(&self, command: Command<T::Message>, priority: Priority, permit: Permit<T::Message>) {
+    let queue: mpsc::Sender<Command<T::Message>> = permit.send(command);
+
+    self
+      .config
+      .metrics
+      .request_queue(priority, queue.max_capacity() - queue.capacity());
+  }
+
+  /// Returns a reference to the channel config.
+  #[inline]
+  pub const fn config(&self) -> &ChannelConfig {
+    &self.config
+  }
+
+  /// Queue every request sent through this handle at `priority`.
+  ///
+  /// Applies to the [`SocketExt`][crate::SocketExt] methods as well, e.g. a
+  /// clone with [`Bulk`][Priority::Bulk] priority for announcements.
+  #[inline]
+  pub const fn with_priority(mut self, priority: Priority) -> Self {
+    self.priority = Some(priority);
+    self
+  }
+
+  /// Returns the round-trip time of the last answered heartbeat ping.
+  ///
+  /// Returns `None` until a ping is answered or if the
+  /// [`heartbeat`][ChannelConfig::heartbeat] is disabled.
+  #[inline]
+  pub fn latency(&self) -> Option<Duration> {
+    self.latency.get()
+  }
+
+  /// Gracefully stops the WebSocket server with the default
+  /// [`shutdown`][Shutdown] options.
+  ///
+  /// Waits for the shutdown to complete, see [`shutdown`][Self::shutdown].
+  #[inline]
+  pub async fn stop(&self) -> Result<(), ChannelError> {
+    self.shutdown(Shutdown::new()).await.map(|_| ())
+  }
+
+  /// Gracefully stops the WebSocket server, see [`Shutdown`].
+  ///
+  /// Requests queued before the shutdown are still sent, requests made
+  /// through any handle afterwards are rejected. Waits for the shutdown to
+  /// complete and returns a summary of it.
+  pub async fn shutdown(&self, options: Shutdown) -> Result<ShutdownSummary, ChannelError> {
+    let (send, recv) = oneshot::channel();
+
+    // Nothing can be queued ahead of the exit from here on
+    self.stopping.store(true, Ordering::Release);
+
+    // Queued behind requests of every priority, which are sent first
+    self
+      .push(Command::ShutdownExit(options, send), Priority::Bulk)
+      .await?;
+
+    recv
+      .await
+      .map_err(|error| ChannelError::new(ErrorKind::ChannRecv, error))
+  }
+
+  /// Forcibly stops the WebSocket server.
+  ///
+  /// Takes effect right away, queued requests are not sent.
+  pub async fn kill(&self) -> Result<(), ChannelError> {
+    if self.send[Priority::Moderation.index()].is_closed() {
+      static ERR: &str = "channel is closed";
+      return Err(ChannelError::msg(ErrorKind::ChannSend, ERR));
+    }
+
+    self.killed.notify_one();
+
+    Ok(())
+  }
+
+  /// Send a request, waiting at most `timeout` for the response.
+  ///
+  /// Overrides the [`timeout`][ChannelConfig::timeout] set in the channel config.
+  #[inline]
+  pub async fn send_with_timeout<P>(&self, payload: P, timeout: Duration) -> SocketResponse<Self>
+  where
+    P: Packet + IntoPayload,
+  {
+    self.request(payload, self.priority(P::REQ_TYPE), Some(timeout)).await
+  }
+
+  /// Send a request, queued ahead of or behind requests of other priorities.
+  ///
+  /// Overrides the default [`priority`][Priority::of] of the request type and
+  /// the priority of the handle.
+  #[inline]
+  pub async fn send_with_priority<P>(&self, payload: P, priority: Priority) -> SocketResponse<Self>
+  where
+    P: Packet + IntoPayload,
+  {
+    self.request(payload, priority, self.config.timeout).await
+  }
+
+  /// Subscribe to the [`events`][EventPacket] accepted by `filter`.
+  ///
+  /// The subscription ends when the WebSocket server task finishes.
+  #[inline]
+  pub fn subscribe(&self, filter: Filter) -> Subscription {
+    let recv: Option<FanoutRecv> = self.fanout.upgrade().map(|fanout| fanout.subscribe());
+    Subscription::new(recv, filter)
+  }
+
+  // ===========================================================================
+  // Private API
+  // ===========================================================================
+
+  /// Reserves a slot in the queue of the handle's priority, or the
+  /// [`Chat`][Priority::Chat] queue if the handle has none.
+  #[cfg(feature = "tower")]
+  pub(crate) fn reserve(&self) -> impl Future<Output = Result<Reserved<T>, ChannelError>> + Send + 'static {
+    let priority: Priority = self.priority.unwrap_or(Priority::Chat);
+    let queue: mpsc::Sender<Command<T::Message>> = self.send[priority.index()].clone();
+
+    async move {
+      match queue.reserve_owned().await {
+        Ok(permit) => Ok(Reserved { priority, permit }),
+        Err(error) => Err(ChannelError::msg(ErrorKind::ChannSend, error)),
+      }
+    }
+  }
+
+  /// Returns the priority requests of type `command` are queued at.
+  #[inline]
+  pub(crate) const fn priority(&self, command: RequestType) -> Priority {
+    match self.priority {
+      Some(priority) => priority,
+      None => Priority::of(command),
+    }
+  }
+
+  /// Send a request packet with the handle's priority and default timeout.
+  #[inline]
+  pub(crate) async fn send_packet(&self, request: RequestPacket) -> Result<ResponsePacket, ChannelError> {
+    self.send_reserved(request, None).await
+  }
+
+  /// Send a request packet with the handle's priority and default timeout.
+  ///
+  /// The request takes the `reserved` slot if it is for the request's queue.
+  pub(crate) async fn send_reserved(
+    &self,
+    request: RequestPacket,
+    reserved: Option<Reserved<T>>,
+  ) -> Result<ResponsePacket, ChannelError> {
+    let command: RequestType = request.command();
+    let priority: Priority = self.priority(command);
+    let permit: Option<Permit<T::Message>> = reserved
+      .filter(|reserved| reserved.priority == priority)
+      .map(|reserved| reserved.permit);
+
+    self
+      .request_packet(request, command.response(), priority, self.config.timeout, permit)
+      .await
+  }
+
+  pub(crate) async fn request<P>(
+    &self,
+    payload: P,
+    priority: Priority,
+    timeout: Option<Duration>,
+  ) -> Result<ResponsePacket, ChannelError>
+  where
+    P: Packet + IntoPayload,
+  {
+    let request: RequestPacket = RequestPacket::new(payload);
+    self.request_packet(request, P::RES_TYPE, priority, timeout, None).await
+  }
+
+  async fn request_packet(
+    &self,
+    request: RequestPacket,
+    expect: ResponseType,
+    priority: Priority,
+    timeout: Option<Duration>,
+    permit: Option<Permit<T::Message>>,
+  ) -> Result<ResponsePacket, ChannelError> {
+    let command: RequestType = request.command();
+    let span: RequestSpan = RequestSpan::new(&request);
+    let mut latency: Duration = Duration::ZERO;
+
+    self.config.metrics.request(command);
+
+    let output: Result<ResponsePacket, ChannelError> = span
+      .instrument(self.retry(request, expect, priority, timeout, permit, &mut latency))
+      .await;
+
+    self.config.metrics.response(command, latency, &output);
+
+    span.finish(&output);
+
+    output
+  }
+
+  /// Send `request`, retrying while rate limited.
+  ///
+  /// The first attempt is queued through `permit`, if any. Adds the time
+  /// spent waiting for responses to `latency`, time spent waiting for the
+  /// client-side limiter is not included.
+  async fn retry(
+    &self,
+    request: RequestPacket,
+    expect: ResponseType,
+    priority: Priority,
+    timeout: Option<Duration>,
+    mut permit: Option<Permit<T::Message>>,
+    latency: &mut Duration,
+  ) -> Result<ResponsePacket, ChannelError> {
+    let mut retries: u32 = self.config.rate_limit_retries;
+
+    loop {
+      let mut throttled: Duration = Duration::ZERO;
+
+      let start: Instant = Instant::now();
+      let output: Result<ResponsePacket, ChannelError> = self
+        .exchange(&request, expect, priority, timeout, permit.take(), &mut throttled)
+        .await;
+
+      *latency += start.elapsed().saturating_sub(throttled);
+
+      let response: ResponsePacket = output?;
+      let status: Option<ResponseStatus> = response.status().filter(|status| !status.is_ok());
+      let limited: bool = status.is_some_and(|status| status.is_rate_limited());
+
+      self.limiter.update(request.command(), limited);
+
+      if limited && retries > 0 {
+        debug!(retries, "rate limited, retrying");
+        retries -= 1;
+        continue;
+      }
+
+      if let Some(status) = status {
+        return Err(ChannelError::status(status));
+      }
+
+      return Ok(response);
+    }
+  }
+
+  async fn exchange(
+    &self,
+    request: &RequestPacket,
+    expect: ResponseType,
+    priority: Priority,
+    timeout: Option<Duration>,
+    permit: Option<Permit<T::Message>>,
+    throttled: &mut Duration,
+  ) -> Result<ResponsePacket, ChannelError> {
+    if self.stopping.load(Ordering::Acquire) {
+      return Err(ChannelError::msg(ErrorKind::Closed, ERR_STOPPING));
+    }
+
+    let (send, recv): (SoloSend<T::Message>, SoloRecv<T::Message>) = oneshot::channel();
+
+    let command: Command<T::Message> = Self::command(request, send)?;
+
+    trace!(request = ?Redacted(request), ?priority, "queueing request");
+
+    match permit {
+      Some(permit) => self.push_reserved(command, priority, permit),
+      None => self.push(command, priority).await?,
+    }
+
+    // From here on, dropping this future deregisters the request.
+    let mut pending: Pending<'_> = Pending::new(&self.cancel, request.request());
+
+    let reply: Reply<(T::Message, Duration)> = match timeout {
+      Some(timeout) => match runtime::timeout(timeout, recv).await {
+        Ok(message) => message.map_err(|error| ChannelError::new(ErrorKind::ChannRecv, error))?,
+        Err(error) => return Err(ChannelError::new(ErrorKind::Timeout, error)),
+      },
+      None => recv
+        .await
+        .map_err(|error| ChannelError::new(ErrorKind::ChannRecv, error))?,
+    };
+
+    pending.disarm();
+
+    let (message, wait): (T::Message, Duration) = reply?;
+
+    *throttled = wait;
+
+    let response: ResponsePacket = decode_message(message)?;
+
+    if response.command() != expect {
+      return Err(ChannelError::msg(
+        ErrorKind::MismatchedResponse,
+        format_args!("expected {:?}, found {:?}", expect, response.command()),
+      ));
+    }
+
+    if response.request() != request.request() {
+      return Err(ChannelError::msg(
+        ErrorKind::MismatchedResponse,
+        format_args!("expected {:?}, found {:?}", request.request(), response.request()),
+      ));
+    }
+
+    Ok(response)
+  }
+
+  #[inline]
+  fn command(
+    packet: &RequestPacket,
+    sender: SoloSend<T::Message>,
+  ) -> Result<Command<T::Message>, ChannelError> {
+    Ok(Command::SendRequest(Request {
+      message: encode(packet).map(T::Message::from_string)?,
+      request: packet.request(),
+      command: packet.command(),
+      oneshot: sender,
+    }))
+  }
+}
+
+impl<T: LocalTransport> Debug for ChannelHandle<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ChannelHandle")
+      .field("config", &self.config)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<T: LocalTransport> Clone for ChannelHandle<T> {
+  #[inline]
+  fn clone(&self) -> Self {
+    Self {
+      send: self.send.clone(),
+      cancel: self.cancel.clone(),
+      config: self.config.clone(),
+      limiter: Arc::clone(&self.limiter),
+      latency: Arc::clone(&self.latency),
+      stopping: Arc::clone(&self.stopping),
+      killed: Arc::clone(&self.killed),
+      fanout: Weak::clone(&self.fanout),
+      priority: self.priority,
+      phantom: PhantomData,
+    }
+  }
+}
+
+// =============================================================================
+// Event Receiver
+// =============================================================================
+
+/// The receiving half of a [`Channel`].
+///
+/// A [`stream`][Stream] of [`events`][EventPacket], ending when the
+/// WebSocket server task finishes.
+pub struct EventReceiver<T: LocalTransport> {
+  recv: ServerRecv<T::Message>,
+  task: JoinHandle<Result<(), ChannelError>>,
+}
+
+impl<T: LocalTransport> EventReceiver<T> {
+  /// Returns a reference to the async task handle.
+  #[inline]
+  pub const fn task(&self) -> &JoinHandle<Result<(), ChannelError>> {
+    &self.task
+  }
+
+  /// Waits for the WebSocket server task to finish.
+  ///
+  /// # Note
+  ///
+  /// [`stop`][ChannelHandle::stop] or [`kill`][ChannelHandle::kill] should be
+  /// called prior, otherwise this may block indefinitely.
+  #[inline]
+  pub async fn join(self) -> Result<(), ChannelError> {
+    match self.task.await {
+      Ok(output) => output,
+      Err(error) => Err(ChannelError::new(ErrorKind::JoinError, error)),
+    }
+  }
+
+  /// Returns a vector of [`events`][EventPacket].
+  pub fn events(&mut self) -> Result<Vec<EventPacket>, ChannelError> {
+    let mut events: Vec<EventPacket> = Vec::new();
+
+    loop {
+      match self.recv.try_recv() {
+        Ok(message) => {
+          events.push(message.and_then(decode_message)?);
+        }
+        Err(TryRecvError::Empty) => {
+          break;
+        }
+        Err(error @ TryRecvError::Disconnected) => {
+          return Err(ChannelError::new(ErrorKind::ChannRecv, error));
+        }
+      }
+    }
+
+    Ok(events)
+  }
+}
+
+impl<T: LocalTransport> Debug for EventReceiver<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("EventReceiver")
+      .field("task", &self.task)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<T: LocalTransport> Stream for EventReceiver<T> {
+  type Item = Result<EventPacket, ChannelError>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self
+      .recv
+      .poll_next_unpin(cx)
+      .map(|message| message.map(|message| message.and_then(decode_message)))
+  }
+}
+
+// =============================================================================
+// Generic Channel
+// =============================================================================
+
+/// A [`ChannelHandle`] and [`EventReceiver`] pair.
+#[derive(Debug)]
+pub struct Channel<T: LocalTransport> {
+  handle: ChannelHandle<T>,
+  events: EventReceiver<T>,
+}
+
+impl<T: Transport> Channel<T> {
+  /// Create a new `Channel` from the given [`transport`][Transport].
+  #[cfg(feature = "tokio")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+  #[inline]
+  pub fn new(transport: T) -> Self
+  where
+    T::Error: Error + Send + Sync,
+  {
+    Self::with_config(transport, ChannelConfig::new())
+  }
+
+  /// Create a new `Channel` from the given [`transport`][Transport] and `config`.
+  ///
+  /// The background task is run by the [`spawner`][ChannelConfig::spawner]
+  /// of `config`, or on the current tokio runtime if none is set.
+  #[cfg(feature = "tokio")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+  pub fn with_config(transport: T, config: ChannelConfig) -> Self
+  where
+    T::Error: Error + Send + Sync,
+  {
+    let spawner: Arc<dyn Spawner> = config
+      .runtime
+      .spawner()
+      .unwrap_or_else(|| Arc::new(TokioSpawner));
+
+    Self::with_spawner(transport, config, spawner)
+  }
+
+  /// Create a new `Channel` from the given [`transport`][Transport] and
+  /// `config`, running the background task with `spawner`.
+  ///
+  /// The spawner replaces the one set in `config`, and is used by other tasks
+  /// of the channel, e.g. a [`Supervisor`][crate::channel::Supervisor].
+  pub fn with_spawner(transport: T, config: ChannelConfig, spawner: Arc<dyn Spawner>) -> Self
+  where
+    T::Error: Error + Send + Sync,
+  {
+    let config: ChannelConfig = config.spawner(Arc::clone(&spawner));
+    let (this, driver) = Self::with_driver(transport, config);
+
+    spawner.spawn(Box::pin(driver));
+
+    this
+  }
+}
+
+impl<T: LocalTransport> Channel<T> {
+  /// Create a new `Channel` without spawning its background task.
+  ///
+  /// The task is returned as a future instead, the channel does nothing until
+  /// it is polled. This allows running the channel on a tokio `LocalSet`, in
+  /// an existing task, or with a [`LocalTransport`] that is not [`Send`].
+  ///
+  /// The spawner of `config` is not used.
+  pub fn with_driver(transport: T, config: ChannelConfig) -> (Self, impl Future<Output = ()>)
+  where
+    T::Error: Error + Send + Sync,
+  {
+    let latency: Arc<Latency> = Arc::new(Latency::new());
+    let (client_send, client_recv) = queues(config.request_buffer.max(1));
+    let (server_send, server_recv) = queue(config.event_buffer);
+    let (cancel_send, cancel_recv) = mpsc::unbounded_channel();
+    let (fanout_send, _) = broadcast::channel(config.subscription_buffer.max(1));
+    let fanout_send: Arc<FanoutSend> = Arc::new(fanout_send);
+
+    let limiter: Arc<Limiter> = Arc::new(Limiter::new(&config));
+    let killed: Arc<Notify> = Arc::new(Notify::new());
+
+    let shared: Shared = Shared {
+      latency: Arc::clone(&latency),
+      limiter: Arc::clone(&limiter),
+      killed: Arc::clone(&killed),
+    };
+
+    let handle: ChannelHandle<T> = ChannelHandle {
+      send: client_send,
+      cancel: cancel_send,
+      limiter,
+      latency,
+      stopping: Arc::new(AtomicBool::new(false)),
+      killed,
+      fanout: Arc::downgrade(&fanout_send),
+      config: config.clone(),
+      priority: None,
+      phantom: PhantomData,
+    };
 
-  async fn send(&mut self, response: Response, message: T) -> Result<(), ChannelError> {
-    // TODO: Probably should track by request type
-    if let Some(oneshot) = self.tracker.remove(&response.request_id) {
-      static ERR: &str = "failed to broadcast response (oneshot)";
+    let (driver, task) = runtime::task(process(
+      transport,
+      server_send,
+      config,
+      fanout_send,
+      shared,
+      client_recv,
+      cancel_recv,
+    ));
 
-      oneshot
-        .send(message)
-        .map_err(|_| ChannelError::msg(ErrorKind::ChannSend, ERR))
-    } else {
-      static ERR: &str = "failed to broadcast response (mailbox)";
+    let events: EventReceiver<T> = EventReceiver {
+      recv: server_recv,
+      task,
+    };
 
-      self
-        .channel
-        .send(message)
-        .await
-        .map_err(|_| ChannelError::msg(ErrorKind::ChannSend, ERR))
-    }
+    (Self { handle, events }, driver)
   }
-}
 
-// =============================================================================
-// Generic Channel
-// =============================================================================
+  /// Split the channel into a [`ChannelHandle`] and [`EventReceiver`].
+  #[inline]
+  pub fn split(self) -> (ChannelHandle<T>, EventReceiver<T>) {
+    (self.handle, self.events)
+  }
 
-#[derive(Debug)]
-pub struct Channel<T: Transport> {
-  recv: ServerRecv<T::Message>,
-  send: ClientSend<T::Message>,
-  task: JoinHandle<Result<(), ChannelError>>,
-  phantom: PhantomData<T>,
-}
+  /// Returns a reference to the sending half of the channel.
+  #[inline]
+  pub const fn handle(&self) -> &ChannelHandle<T> {
+    &self.handle
+  }
 
-impl<T: Transport> Channel<T> {
-  const BUFFER_CLIENT: usize = 0x20;
-  const BUFFER_SERVER: usize = 0x20;
+  /// Returns a reference to the channel config.
+  #[inline]
+  pub const fn config(&self) -> &ChannelConfig {
+    self.handle.config()
+  }
 
-  /// Create a new `Channel` from the given [`transport`][Transport].
-  pub fn new(transport: T) -> Self
-  where
-    T::Error: Error + Send,
-  {
-    let (client_send, client_recv) = mpsc::channel(Self::BUFFER_CLIENT);
-    let (server_send, server_recv) = mpsc::channel(Self::BUFFER_SERVER);
+  /// Returns the round-trip time of the last answered heartbeat ping.
+  ///
+  /// See [`ChannelHandle::latency`].
+  #[inline]
+  pub fn latency(&self) -> Option<Duration> {
+    self.handle.latency()
+  }
 
-    Self {
-      recv: ReceiverStream::new(server_recv),
-      send: client_send,
-      task: task::spawn(process(transport, server_send, client_recv)),
-      phantom: PhantomData,
-    }
+  /// Subscribe to the [`events`][EventPacket] accepted by `filter`.
+  ///
+  /// See [`ChannelHandle::subscribe`].
+  #[inline]
+  pub fn subscribe(&self, filter: Filter) -> Subscription {
+    self.handle.subscribe(filter)
   }
 
   /// Returns a reference to the async task handle.
   #[inline]
   pub const fn task(&self) -> &JoinHandle<Result<(), ChannelError>> {
-    &self.task
+    self.events.task()
   }
 
   /// Waits for the WebSocket server task to finish.
@@ -162,98 +1156,89 @@ impl<T: Transport> Channel<T> {
   /// otherwise this may block indefinitely.
   #[inline]
   pub async fn join(self) -> Result<(), ChannelError> {
-    match self.task.await {
-      Ok(output) => output,
-      Err(error) => Err(ChannelError::new(ErrorKind::JoinError, error)),
-    }
+    self.events.join().await
   }
 
-  /// Gracefully stops the WebSocket server.
+  /// Gracefully stops the WebSocket server with the default
+  /// [`shutdown`][Shutdown] options.
+  ///
+  /// Waits for the shutdown to complete, see [`shutdown`][Self::shutdown].
   #[inline]
   pub async fn stop(&self) -> Result<(), ChannelError> {
-    self.push(Command::ShutdownExit).await
+    self.handle.stop().await
   }
 
-  /// Forcibly stops the WebSocket server.
+  /// Gracefully stops the WebSocket server, see [`Shutdown`].
   #[inline]
-  pub async fn kill(&self) -> Result<(), ChannelError> {
-    self.push(Command::ShutdownKill).await
-  }
-
-  /// Returns a [`stream`][Stream] of [`events`][EventPacket].
-  pub fn event_stream(&mut self) -> impl Stream<Item = Result<EventPacket, ChannelError>> + '_ {
-    (&mut self.recv).map(decode_message)
+  pub async fn shutdown(&self, options: Shutdown) -> Result<ShutdownSummary, ChannelError> {
+    self.handle.shutdown(options).await
   }
 
-  /// Returns a vector of [`events`][EventPacket].
-  pub fn events(&mut self) -> Result<Vec<EventPacket>, ChannelError> {
-    let mut events: Vec<EventPacket> = Vec::new();
-
-    loop {
-      match self.recv.as_mut().try_recv() {
-        Ok(message) => {
-          events.push(decode_message(message)?);
-        }
-        Err(TryRecvError::Empty) => {
-          break;
-        }
-        Err(error @ TryRecvError::Disconnected) => {
-          return Err(ChannelError::new(ErrorKind::ChannRecv, error));
-        }
-      }
-    }
-
-    Ok(events)
+  /// Forcibly stops the WebSocket server.
+  #[inline]
+  pub async fn kill(&self) -> Result<(), ChannelError> {
+    self.handle.kill().await
   }
 
-  // ===========================================================================
-  // Private API
-  // ===========================================================================
-
-  async fn push(&self, command: Command<T::Message>) -> Result<(), ChannelError> {
-    self
-      .send
-      .send(command)
-      .await
-      .map_err(|error| ChannelError::new(ErrorKind::ChannSend, error))
+  /// Send a request, waiting at most `timeout` for the response.
+  ///
+  /// Overrides the [`timeout`][ChannelConfig::timeout] set in the channel config.
+  #[inline]
+  pub async fn send_with_timeout<P>(&self, payload: P, timeout: Duration) -> SocketResponse<Self>
+  where
+    P: Packet + IntoPayload,
+  {
+    self.handle.send_with_timeout(payload, timeout).await
   }
 
-  async fn send<P>(&self, payload: P) -> Result<ResponsePacket, ChannelError>
+  /// Send a request, queued ahead of or behind requests of other priorities.
+  ///
+  /// Overrides the default [`priority`][Priority::of] of the request type.
+  #[inline]
+  pub async fn send_with_priority<P>(&self, payload: P, priority: Priority) -> SocketResponse<Self>
   where
     P: Packet + IntoPayload,
   {
-    let (send, recv): (SoloSend<T::Message>, SoloRecv<T::Message>) = oneshot::channel();
-
-    let request: RequestPacket = RequestPacket::new(payload);
-    let command: Command<T::Message> = Self::request(&request, send)?;
+    self.handle.send_with_priority(payload, priority).await
+  }
 
-    self.push(command).await?;
+  /// Returns a [`stream`][Stream] of [`events`][EventPacket].
+  ///
+  /// Errors raised by the connection itself (transport failures, malformed
+  /// frames) are delivered through this stream as well.
+  #[inline]
+  pub fn event_stream(&mut self) -> impl Stream<Item = Result<EventPacket, ChannelError>> + '_ {
+    &mut self.events
+  }
 
-    let response: ResponsePacket = recv
-      .await
-      .map_err(|error| ChannelError::new(ErrorKind::ChannRecv, error))
-      .and_then(decode_message)?;
+  /// Returns a vector of [`events`][EventPacket].
+  #[inline]
+  pub fn events(&mut self) -> Result<Vec<EventPacket>, ChannelError> {
+    self.events.events()
+  }
+}
 
-    assert_eq!(response.command(), P::RES_TYPE);
-    assert_eq!(response.request(), request.request());
+fn queues<T>(buffer: usize) -> (ClientSend<T>, ClientRecv<T>) {
+  let (moderation_send, moderation_recv) = mpsc::channel(buffer);
+  let (chat_send, chat_recv) = mpsc::channel(buffer);
+  let (bulk_send, bulk_recv) = mpsc::channel(buffer);
 
-    if let Some(status) = response.status() {
-      return Err(ChannelError::status(status));
-    }
+  (
+    [moderation_send, chat_send, bulk_send],
+    [moderation_recv, chat_recv, bulk_recv],
+  )
+}
 
-    Ok(response)
-  }
+/// Receive the next command, in order of [`priority`][Priority].
+async fn next_command<T>(recv: &mut ClientRecv<T>) -> Option<Command<T>> {
+  let [moderation, chat, bulk] = recv;
 
-  #[inline]
-  fn request(
-    packet: &RequestPacket,
-    sender: SoloSend<T::Message>,
-  ) -> Result<Command<T::Message>, ChannelError> {
-    Ok(Command::SendRequest(Request {
-      message: encode(packet).map(T::Message::from_string)?,
-      request: packet.request(),
-      oneshot: sender,
-    }))
+  tokio::select! {
+    biased;
+    Some(command) = moderation.recv() => Some(command),
+    Some(command) = chat.recv() => Some(command),
+    Some(command) = bulk.recv() => Some(command),
+    else => None,
   }
 }
 
@@ -267,8 +1252,19 @@ fn decode_message<T: Message, U: DeserializeOwned>(message: T) -> Result<U, Chan
 
 #[inline]
 fn into_string<T: Message>(message: T) -> Result<String, ChannelError> {
+  match classify(message)? {
+    Frame::Data(data) => Ok(data),
+    Frame::Control(_) | Frame::Close(_) => {
+      static ERR: &str = "expected data frame";
+      Err(ChannelError::msg(ErrorKind::Protocol, ERR))
+    }
+  }
+}
+
+#[inline]
+fn classify<T: Message>(message: T) -> Result<Frame, ChannelError> {
   message
-    .into_string()
+    .classify()
     .map_err(|error| ChannelError::new(ErrorKind::Socket, error))
 }
 
@@ -286,7 +1282,19 @@ fn decode<'de, T: Deserialize<'de>>(data: &'de str) -> Result<T, ChannelError> {
 // Socket Implementation
 // =============================================================================
 
-impl<T: Transport> Socket for Channel<T> {
+impl<T: LocalTransport> Socket for ChannelHandle<T> {
+  type Error = ChannelError;
+
+  #[inline]
+  async fn send<P>(&self, payload: P) -> SocketResponse<Self>
+  where
+    P: Packet + IntoPayload,
+  {
+    self.request(payload, self.priority(P::REQ_TYPE), self.config.timeout).await
+  }
+}
+
+impl<T: LocalTransport> Socket for Channel<T> {
   type Error = ChannelError;
 
   #[inline]
@@ -294,7 +1302,35 @@ impl<T: Transport> Socket for Channel<T> {
   where
     P: Packet + IntoPayload,
   {
-    Channel::send(self, payload).await
+    self.handle.send(payload).await
+  }
+}
+
+impl<T: LocalTransport> Handler for ChannelHandle<T> {
+  type Error = ChannelError;
+
+  #[inline]
+  async fn call(&self, request: RequestPacket) -> Result<ResponsePacket, Self::Error> {
+    self.send_packet(request).await
+  }
+
+  #[inline]
+  fn status(error: &Self::Error) -> Option<ResponseStatus> {
+    error.response_status()
+  }
+}
+
+impl<T: LocalTransport> Handler for Channel<T> {
+  type Error = ChannelError;
+
+  #[inline]
+  async fn call(&self, request: RequestPacket) -> Result<ResponsePacket, Self::Error> {
+    self.handle.call(request).await
+  }
+
+  #[inline]
+  fn status(error: &Self::Error) -> Option<ResponseStatus> {
+    error.response_status()
   }
 }
 
@@ -302,64 +1338,367 @@ impl<T: Transport> Socket for Channel<T> {
 // Internal Message Handler
 // =============================================================================
 
+/// State shared between the handles and the run loop.
+struct Shared {
+  latency: Arc<Latency>,
+  limiter: Arc<Limiter>,
+  killed: Arc<Notify>,
+}
+
 async fn process<T>(
   transport: T,
   send: ServerSend<T::Message>,
+  config: ChannelConfig,
+  fanout: Arc<FanoutSend>,
+  shared: Shared,
   mut recv: ClientRecv<T::Message>,
+  mut cancel: CancelRecv,
+) -> Result<(), ChannelError>
+where
+  T: LocalTransport,
+  T::Error: Error + Send + Sync,
+{
+  // Tracker for request/response channels
+  let mut mailbox: MailBox<T::Message> =
+    MailBox::new(send, config.metrics.clone(), config.overflow, fanout);
+
+  // Graceful shutdown state
+  let mut closing: Option<Closing> = None;
+
+  debug!("channel started");
+
+  let output: Result<(), ChannelError> = run(
+    transport,
+    &config,
+    &shared,
+    &mut recv,
+    &mut cancel,
+    &mut mailbox,
+    &mut closing,
+  )
+  .await;
+
+  // A shutdown in progress is reported however the loop ended
+  let Some(state) = closing else {
+    debug!(result = ?output, "channel stopped");
+    return output;
+  };
+
+  state.finish(&mut mailbox);
+
+  info!(result = ?output, "channel shut down");
+
+  output
+}
+
+/// Send a tracked request, rejecting it if the transport fails.
+async fn send_request<T>(
+  ssend: &mut SplitSink<T, T::Message>,
+  mailbox: &mut MailBox<T::Message>,
+  request: RequestID,
+  message: T::Message,
+) -> Result<(), ChannelError>
+where
+  T: LocalTransport,
+  T::Error: Error + Send + Sync,
+{
+  if let Err(error) = ssend.send(message).await {
+    let error: ChannelError = ChannelError::new(ErrorKind::Transport, error);
+    let clone: ChannelError = error.replicate();
+
+    warn!(%error, "failed to send request");
+
+    mailbox.reject(request, error);
+
+    return Err(clone);
+  }
+
+  Ok(())
+}
+
+async fn run<T>(
+  transport: T,
+  config: &ChannelConfig,
+  shared: &Shared,
+  recv: &mut ClientRecv<T::Message>,
+  cancel: &mut CancelRecv,
+  mailbox: &mut MailBox<T::Message>,
+  closing: &mut Option<Closing>,
 ) -> Result<(), ChannelError>
 where
-  T: Transport,
-  T::Error: Error + Send,
+  T: LocalTransport,
+  T::Error: Error + Send + Sync,
 {
   // Split the socket into writer/reader
   let (mut ssend, mut srecv): (SplitSink<T, T::Message>, SplitStream<T>) = transport.split();
 
-  // Tracker for request/response channels
-  let mut mailbox: MailBox<T::Message> = MailBox::new(send);
+  let Shared {
+    ref latency,
+    ref limiter,
+    ref killed,
+  } = *shared;
+
+  // Keepalive ping state
+  let mut pulse: Pulse = Pulse::new(config.heartbeat);
+
+  // Request waiting for the limiter, no other command is picked meanwhile
+  let mut throttled: Option<Throttled<T::Message>> = None;
 
   'runloop: loop {
+    if let Some(ref mut state) = *closing {
+      if state.stage == Stage::Draining && (mailbox.is_idle() || state.expired()) {
+        debug!(drained = mailbox.is_idle(), "stopped waiting for outstanding responses");
+
+        state.stage = Stage::Closing;
+
+        if state.options.disconnect {
+          debug!("leaving chat");
+
+          let packet: RequestPacket = RequestPacket::new(ChatDisconnect);
+          let message: T::Message = encode(&packet).map(T::Message::from_string)?;
+
+          if let Err(error) = ssend.send(message).await {
+            return Err(ChannelError::new(ErrorKind::Transport, error));
+          }
+
+          state.disconnecting(packet.request());
+        }
+      }
+
+      if matches!(state.stage, Stage::Disconnecting(_)) && state.expired() {
+        debug!("stopped waiting for the disconnect event");
+        state.stage = Stage::Closing;
+      }
+
+      if state.stage == Stage::Closing {
+        break 'runloop;
+      }
+    }
+
+    let remaining: Option<Duration> = closing.as_ref().and_then(Closing::remaining);
+    let delay: Option<Duration> = throttled.as_ref().map(Throttled::remaining);
+
     tokio::select! {
       // Process client commands
-      Some(command) = recv.recv() => {
+      command = next_command(recv), if throttled.is_none() => {
         match command {
-          Command::SendRequest(Request { message, request, oneshot }) => {
-            mailbox.track(request, oneshot);
+          Some(Command::SendRequest(Request { oneshot, .. })) if closing.is_some() => {
+            // The caller may have gone away, there is nobody else to tell.
+            let _ignore = oneshot.send(Err(ChannelError::msg(ErrorKind::Closed, ERR_STOPPING)));
+
+            if let Some(ref mut state) = *closing {
+              state.summary.abandoned += 1;
+            }
+          }
+          Some(Command::SendRequest(Request { message, request, command, oneshot })) => {
+            if !mailbox.track(request, oneshot) {
+              continue 'runloop;
+            }
 
-            if let Err(error) = ssend.send(message).await {
-              return Err(ChannelError::new(ErrorKind::ChannSend, error));
+            match limiter.reserve(command) {
+              Ok(()) => send_request(&mut ssend, mailbox, request, message).await?,
+              Err(delay) => {
+                trace!(?delay, "waiting for the rate limiter");
+                throttled = Some(Throttled::new(message, request, command, delay));
+              }
             }
           }
-          Command::ShutdownExit => {
-            let Ok(mut transport) = ssend.reunite(srecv) else {
-              static ERR: &str = "failed to reunite split `stream + sink`";
-              return Err(ChannelError::msg(ErrorKind::Socket, ERR));
+          Some(Command::ShutdownExit(options, reply)) => {
+            // A shutdown already in progress is not restarted
+            if closing.is_none() {
+              info!(?options, "graceful shutdown started");
+              *closing = Some(Closing::new(options, reply, mailbox));
+            }
+          }
+          None => {
+            debug!("all channel handles dropped");
+            break 'runloop;
+          }
+        }
+      }
+      // Stop right away, even while a request waits for the limiter
+      () = killed.notified() => {
+        info!("channel killed");
+        break 'runloop;
+      }
+      // Process dropped requests
+      Some(request) = cancel.recv() => {
+        if throttled.as_ref().is_some_and(|held| held.request == request) {
+          throttled = None;
+        }
+
+        mailbox.forget(request);
+      }
+      // Send the request held back by the limiter
+      () = runtime::sleep(delay.unwrap_or_default()), if delay.is_some() => {
+        let Some(mut held) = throttled.take() else {
+          continue 'runloop;
+        };
+
+        // Dropped by the caller while waiting
+        if !mailbox.is_tracked(held.request) {
+          continue 'runloop;
+        }
+
+        match limiter.reserve(held.command) {
+          Ok(()) => {
+            mailbox.throttled(held.request, held.picked.elapsed());
+            send_request(&mut ssend, mailbox, held.request, held.message).await?;
+          }
+          Err(delay) => {
+            held.since = Instant::now();
+            held.delay = delay;
+            throttled = Some(held);
+          }
+        }
+      }
+      // Wake up when a shutdown step runs out of time
+      () = runtime::sleep(remaining.unwrap_or_default()), if remaining.is_some() => {}
+      // Send keepalive pings
+      beat = pulse.tick() => {
+        match beat {
+          Beat::Ping(payload) => {
+            let Some(ping) = T::Message::ping(payload) else {
+              debug!("transport cannot send pings, heartbeat disabled");
+              pulse.stop();
+              continue 'runloop;
             };
 
-            transport
-              .shutdown()
-              .await
-              .map_err(|error| ChannelError::new(ErrorKind::Socket, error))?;
+            trace!("sending heartbeat ping");
 
-            break 'runloop;
+            if let Err(error) = ssend.send(ping).await {
+              return Err(ChannelError::new(ErrorKind::Transport, error));
+            }
           }
-          Command::ShutdownKill => {
-            break 'runloop;
+          Beat::Dead => {
+            static ERR: &str = "heartbeat pings were not answered";
+
+            let error: ChannelError = ChannelError::msg(ErrorKind::Dead, ERR);
+
+            warn!("heartbeat pings were not answered, connection is dead");
+
+            mailbox.reject_all(&error);
+            mailbox.publish(Err(error.replicate()))?;
+
+            // The peer is likely gone, don't wait long for the close handshake
+            if let Ok(mut transport) = ssend.reunite(srecv) {
+              let _ignore = runtime::timeout(pulse.period(), transport.shutdown()).await;
+            }
+
+            mailbox.drain().await;
+
+            return Err(error);
           }
         }
       }
+      // Deliver events held back by a full event stream
+      () = mailbox.channel.ready(), if !mailbox.backlog.is_empty() => {
+        mailbox.advance();
+      }
       // Process WebSocket messages
-      Some(message) = srecv.next() => {
-        let Ok(message) = message else {
-          panic!("TODO");
+      message = srecv.next() => {
+        let message: T::Message = match message {
+          Some(Ok(message)) => message,
+          Some(Err(error)) => {
+            let error: ChannelError = ChannelError::new(ErrorKind::Transport, error);
+            let clone: ChannelError = error.replicate();
+
+            warn!(%error, "transport error");
+
+            mailbox.reject_all(&error);
+            mailbox.publish(Err(error))?;
+            mailbox.drain().await;
+
+            return Err(clone);
+          }
+          None => {
+            static ERR: &str = "transport ended";
+
+            info!("transport ended");
+
+            mailbox.reject_all(&ChannelError::msg(ErrorKind::Closed, ERR));
+            mailbox.drain().await;
+
+            break 'runloop;
+          }
+        };
+
+        pulse.alive();
+
+        let text: String = match classify(message) {
+          Ok(Frame::Data(text)) => text,
+          Ok(Frame::Control(Control::Ping(payload))) => {
+            if let Some(pong) = T::Message::pong(payload) {
+              if let Err(error) = ssend.send(pong).await {
+                return Err(ChannelError::new(ErrorKind::Transport, error));
+              }
+            }
+
+            continue 'runloop;
+          }
+          Ok(Frame::Control(Control::Pong(payload))) => {
+            pulse.pong(&payload, latency);
+            trace!(latency = ?latency.get(), "received pong");
+            continue 'runloop;
+          }
+          Ok(Frame::Close(frame)) => {
+            info!(?frame, "connection closed by server");
+
+            // Flush any pending close reply queued by the transport
+            let _ignore = ssend.flush().await;
+
+            let error: ChannelError = ChannelError::closed(frame);
+
+            mailbox.reject_all(&error);
+            mailbox.publish(Err(error))?;
+            mailbox.drain().await;
+
+            break 'runloop;
+          }
+          Err(error) => {
+            warn!(%error, "invalid frame");
+            mailbox.publish(Err(error))?;
+            continue 'runloop;
+          }
         };
 
-        let text: String = into_string(message)?;
-        let data: Response = decode(text.as_str())?;
+        let response: Result<Response, ChannelError> = decode(text.as_str())
+          .map_err(|error| ChannelError::msg(ErrorKind::Protocol, error));
 
-        mailbox.send(data, T::Message::from_string(text)).await?;
+        if let (Some(state), Ok(data)) = (closing.as_mut(), response.as_ref()) {
+          state.observe(data, text.as_str());
+        }
+
+        match response {
+          Ok(data) => mailbox.send(data, text)?,
+          Err(error) => {
+            warn!(%error, "failed to decode message");
+            mailbox.publish(Err(error))?;
+          }
+        }
       }
     }
   }
 
-  Ok(())
+  // The connection may have ended before the shutdown completed
+  match *closing {
+    Some(ref state) if state.stage == Stage::Closing => close(ssend, srecv).await,
+    Some(_) | None => Ok(()),
+  }
+}
+
+async fn close<T>(ssend: SplitSink<T, T::Message>, srecv: SplitStream<T>) -> Result<(), ChannelError>
+where
+  T: LocalTransport,
+  T::Error: Error + Send + Sync,
+{
+  let Ok(mut transport) = ssend.reunite(srecv) else {
+    static ERR: &str = "failed to reunite split `stream + sink`";
+    return Err(ChannelError::msg(ErrorKind::Socket, ERR));
+  };
+
+  transport
+    .shutdown()
+    .await
+    .map_err(|error| ChannelError::new(ErrorKind::Socket, error))
 }
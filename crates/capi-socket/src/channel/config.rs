@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::channel::metrics::Metrics;
+use crate::channel::Heartbeat;
+use crate::channel::Overflow;
+use crate::channel::RateLimit;
+use crate::channel::runtime::Runtime;
+use crate::channel::Spawner;
+
+// =============================================================================
+// Channel Config
+// =============================================================================
+
+#[derive(Clone, Debug)]
+pub struct ChannelConfig {
+  pub(crate) timeout: Option<Duration>,
+  pub(crate) rate_limit: Option<RateLimit>,
+  pub(crate) moderation_rate_limit: Option<RateLimit>,
+  pub(crate) rate_limit_retries: u32,
+  pub(crate) request_buffer: usize,
+  pub(crate) event_buffer: usize,
+  pub(crate) subscription_buffer: usize,
+  pub(crate) overflow: Overflow,
+  pub(crate) heartbeat: Option<Heartbeat>,
+  pub(crate) metrics: Metrics,
+  pub(crate) runtime: Runtime,
+}
+
+impl ChannelConfig {
+  /// The default time to wait for a response.
+  pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+  /// The default number of queued requests per [`priority`][crate::channel::Priority].
+  pub const DEFAULT_REQUEST_BUFFER: usize = 0x20;
+
+  /// The default number of queued events.
+  pub const DEFAULT_EVENT_BUFFER: usize = 0x20;
+
+  /// The default number of queued events per subscription.
+  pub const DEFAULT_SUBSCRIPTION_BUFFER: usize = 0x100;
+
+  #[inline]
+  pub const fn new() -> Self {
+    Self {
+      timeout: Some(Self::DEFAULT_TIMEOUT),
+      rate_limit: None,
+      moderation_rate_limit: None,
+      rate_limit_retries: 0,
+      request_buffer: Self::DEFAULT_REQUEST_BUFFER,
+      event_buffer: Self::DEFAULT_EVENT_BUFFER,
+      subscription_buffer: Self::DEFAULT_SUBSCRIPTION_BUFFER,
+      overflow: Overflow::Block,
+      heartbeat: None,
+      metrics: Metrics::new(),
+      runtime: Runtime::new(),
+    }
+  }
+
+  /// Set the time to wait for a response, `None` waits indefinitely.
+  #[inline]
+  pub const fn timeout(mut self, timeout: Option<Duration>) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// Set the budget for outgoing requests, `None` disables throttling.
+  ///
+  /// Requests are picked in [`priority`][crate::channel::Priority] order before taking from
+  /// the budget. A picked request holds its place while it waits, later
+  /// requests queue behind it.
+  #[inline]
+  pub const fn rate_limit(mut self, rate_limit: Option<RateLimit>) -> Self {
+    self.rate_limit = rate_limit;
+    self
+  }
+
+  /// Set a separate budget for moderation commands.
+  ///
+  /// If `None`, moderation commands share the [`rate_limit`][Self::rate_limit].
+  #[inline]
+  pub const fn moderation_rate_limit(mut self, rate_limit: Option<RateLimit>) -> Self {
+    self.moderation_rate_limit = rate_limit;
+    self
+  }
+
+  /// Set the number of times a request is retried after hitting the
+  /// server rate limit, `0` returns the status error to the caller.
+  #[inline]
+  pub const fn rate_limit_retries(mut self, retries: u32) -> Self {
+    self.rate_limit_retries = retries;
+    self
+  }
+
+  /// Set the number of requests that may be queued per priority before
+  /// senders wait.
+  #[inline]
+  pub const fn request_buffer(mut self, buffer: usize) -> Self {
+    self.request_buffer = buffer;
+    self
+  }
+
+  /// Set the number of events that may be queued for the event stream.
+  #[inline]
+  pub const fn event_buffer(mut self, buffer: usize) -> Self {
+    self.event_buffer = buffer;
+    self
+  }
+
+  /// Set the number of events that may be queued for each
+  /// [`subscription`][crate::channel::Subscription] before it lags.
+  #[inline]
+  pub const fn subscription_buffer(mut self, buffer: usize) -> Self {
+    self.subscription_buffer = buffer;
+    self
+  }
+
+  /// Set what happens to events when the event stream is full.
+  #[inline]
+  pub const fn overflow(mut self, overflow: Overflow) -> Self {
+    self.overflow = overflow;
+    self
+  }
+
+  /// Set the keepalive pings sent to detect a dead connection, `None`
+  /// disables them.
+  #[inline]
+  pub const fn heartbeat(mut self, heartbeat: Option<Heartbeat>) -> Self {
+    self.heartbeat = heartbeat;
+    self
+  }
+
+  /// Set the spawner that runs the background tasks of the channel.
+  ///
+  /// Defaults to [`TokioSpawner`][crate::channel::TokioSpawner] if the `tokio`
+  /// feature is enabled. Without it, channels are created with
+  /// [`Channel::with_spawner`][crate::channel::Channel::with_spawner] or
+  /// [`Channel::with_driver`][crate::channel::Channel::with_driver].
+  #[inline]
+  pub fn spawner(mut self, spawner: Arc<dyn Spawner>) -> Self {
+    self.runtime.set(spawner);
+    self
+  }
+
+  /// Set the recorder that receives channel measurements.
+  #[cfg(feature = "metrics")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+  #[inline]
+  pub fn recorder(mut self, recorder: Arc<dyn crate::channel::Recorder>) -> Self {
+    self.metrics.set(recorder);
+    self
+  }
+}
+
+impl Default for ChannelConfig {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -5,6 +5,8 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result;
 
+use crate::channel::CloseFrame;
+
 // =============================================================================
 // Channel Error
 // =============================================================================
@@ -16,8 +18,41 @@ pub struct ChannelError {
 }
 
 impl ChannelError {
+  /// Returns the kind of this error.
+  #[inline]
+  pub const fn kind(&self) -> ErrorKind {
+    self.kind
+  }
+
+  /// Returns the response status of this error, if any.
+  #[inline]
+  pub const fn response_status(&self) -> Option<ResponseStatus> {
+    match self.source {
+      ErrorSource::Status(status) => Some(status),
+      _ => None,
+    }
+  }
+
+  /// Returns the close frame sent by the server, if any.
+  #[inline]
+  pub const fn close_frame(&self) -> Option<&CloseFrame> {
+    match self.source {
+      ErrorSource::Closed(Some(ref frame)) => Some(frame),
+      _ => None,
+    }
+  }
+
+  /// Returns the number of events a lagging subscriber missed, if any.
+  #[inline]
+  pub const fn missed(&self) -> Option<u64> {
+    match self.source {
+      ErrorSource::Lagged(missed) => Some(missed),
+      _ => None,
+    }
+  }
+
   #[inline]
-  pub(crate) fn new(kind: ErrorKind, source: impl Error + Send + 'static) -> Self {
+  pub(crate) fn new(kind: ErrorKind, source: impl Error + Send + Sync + 'static) -> Self {
     Self {
       kind,
       source: ErrorSource::Source(Box::new(source)),
@@ -39,6 +74,33 @@ impl ChannelError {
       source: ErrorSource::Status(status),
     }
   }
+
+  #[inline]
+  pub(crate) fn closed(frame: Option<CloseFrame>) -> Self {
+    Self {
+      kind: ErrorKind::Closed,
+      source: ErrorSource::Closed(frame),
+    }
+  }
+
+  #[inline]
+  pub(crate) fn lagged(missed: u64) -> Self {
+    Self {
+      kind: ErrorKind::Lagged,
+      source: ErrorSource::Lagged(missed),
+    }
+  }
+
+  /// Create a copy of this error with the source flattened into a message.
+  #[inline]
+  pub(crate) fn replicate(&self) -> Self {
+    match self.source {
+      ErrorSource::Status(status) => Self::status(status),
+      ErrorSource::Closed(ref frame) => Self::closed(frame.clone()),
+      ErrorSource::Lagged(missed) => Self::lagged(missed),
+      ref source => Self::msg(self.kind, source),
+    }
+  }
 }
 
 impl Display for ChannelError {
@@ -52,12 +114,15 @@ impl Error for ChannelError {
     match self.source {
       ErrorSource::Source(ref inner) => Some(&**inner),
       ErrorSource::Status(_) => None,
+      ErrorSource::Closed(_) => None,
+      ErrorSource::Lagged(_) => None,
       ErrorSource::String(_) => None,
     }
   }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum ErrorKind {
   Socket,
   Encode,
@@ -66,6 +131,29 @@ pub enum ErrorKind {
   ChannRecv,
   ChannSend,
   JoinError,
+  /// The underlying transport failed to connect, or to send or receive a
+  /// frame.
+  Transport,
+  /// The server sent a frame that does not follow the chat API protocol.
+  Protocol,
+  /// A request was sent with an ID that is already awaiting a response.
+  DuplicateRequest,
+  /// A response did not match the request it was routed to.
+  MismatchedResponse,
+  /// The server did not accept the WebSocket upgrade.
+  Handshake,
+  /// The server closed the connection.
+  Closed,
+  /// No response was received in time.
+  Timeout,
+  /// A subscriber fell behind and missed events.
+  Lagged,
+  /// The event stream was full.
+  Overflow,
+  /// The connection stopped answering heartbeat pings.
+  Dead,
+  /// No spawner was configured to run a background task.
+  Spawn,
 }
 
 impl Display for ErrorKind {
@@ -78,14 +166,27 @@ impl Display for ErrorKind {
       Self::ChannRecv => write!(f, "recv error"),
       Self::ChannSend => write!(f, "send error"),
       Self::JoinError => write!(f, "join error"),
+      Self::Transport => write!(f, "transport error"),
+      Self::Protocol => write!(f, "protocol error"),
+      Self::DuplicateRequest => write!(f, "duplicate request"),
+      Self::MismatchedResponse => write!(f, "mismatched response"),
+      Self::Handshake => write!(f, "handshake error"),
+      Self::Closed => write!(f, "connection closed"),
+      Self::Timeout => write!(f, "timeout error"),
+      Self::Lagged => write!(f, "lagged"),
+      Self::Overflow => write!(f, "overflow"),
+      Self::Dead => write!(f, "connection dead"),
+      Self::Spawn => write!(f, "spawn error"),
     }
   }
 }
 
 #[derive(Debug)]
 enum ErrorSource {
-  Source(Box<dyn Error + Send + 'static>),
+  Source(Box<dyn Error + Send + Sync + 'static>),
   Status(ResponseStatus),
+  Closed(Option<CloseFrame>),
+  Lagged(u64),
   String(String),
 }
 
@@ -94,6 +195,9 @@ impl Display for ErrorSource {
     match self {
       Self::Source(inner) => Display::fmt(inner, f),
       Self::Status(inner) => Display::fmt(inner.as_str(), f),
+      Self::Closed(Some(inner)) => write!(f, "{} {}", inner.code, inner.reason),
+      Self::Closed(None) => write!(f, "no close frame"),
+      Self::Lagged(inner) => write!(f, "missed {inner} events"),
       Self::String(inner) => Display::fmt(inner, f),
     }
   }
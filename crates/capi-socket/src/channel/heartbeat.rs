@@ -0,0 +1,179 @@
+use futures_timer::Delay;
+use std::future;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+// =============================================================================
+// Heartbeat Config
+// =============================================================================
+
+/// Keepalive pings sent by the channel to detect dead connections.
+#[derive(Clone, Copy, Debug)]
+pub struct Heartbeat {
+  interval: Duration,
+  misses: u32,
+}
+
+impl Heartbeat {
+  /// The shortest accepted time between pings.
+  pub const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+  /// Send a ping every `interval`.
+  ///
+  /// Intervals shorter than [`MIN_INTERVAL`][Self::MIN_INTERVAL] are raised
+  /// to it.
+  #[inline]
+  pub const fn new(interval: Duration) -> Self {
+    let interval: Duration = if interval.as_nanos() < Self::MIN_INTERVAL.as_nanos() {
+      Self::MIN_INTERVAL
+    } else {
+      interval
+    };
+
+    Self {
+      interval,
+      misses: 3,
+    }
+  }
+
+  /// Set the number of consecutive unanswered pings after which the
+  /// connection is declared dead.
+  ///
+  /// Any frame received from the server counts as an answer. A value of `0`
+  /// is treated as `1`.
+  #[inline]
+  pub const fn misses(mut self, misses: u32) -> Self {
+    self.misses = if misses == 0 { 1 } else { misses };
+    self
+  }
+
+  /// Returns the time between pings.
+  #[inline]
+  pub const fn interval(&self) -> Duration {
+    self.interval
+  }
+}
+
+// =============================================================================
+// Heartbeat Latency
+// =============================================================================
+
+/// The round-trip time of the most recent answered ping.
+#[derive(Debug)]
+pub(crate) struct Latency {
+  nanos: AtomicU64,
+}
+
+impl Latency {
+  const UNKNOWN: u64 = u64::MAX;
+
+  #[inline]
+  pub(crate) const fn new() -> Self {
+    Self {
+      nanos: AtomicU64::new(Self::UNKNOWN),
+    }
+  }
+
+  pub(crate) fn get(&self) -> Option<Duration> {
+    match self.nanos.load(Ordering::Relaxed) {
+      Self::UNKNOWN => None,
+      nanos => Some(Duration::from_nanos(nanos)),
+    }
+  }
+
+  fn set(&self, latency: Duration) {
+    let nanos: u64 = u64::try_from(latency.as_nanos()).unwrap_or(Self::UNKNOWN - 1);
+    self.nanos.store(nanos, Ordering::Relaxed);
+  }
+}
+
+// =============================================================================
+// Heartbeat State
+// =============================================================================
+
+/// The outcome of a heartbeat tick.
+pub(crate) enum Beat {
+  /// Send a ping carrying the given payload.
+  Ping(Vec<u8>),
+  /// Too many pings went unanswered.
+  Dead,
+}
+
+pub(crate) struct Pulse {
+  interval: Option<(Delay, Duration)>,
+  limit: u32,
+  misses: u32,
+  sequence: u64,
+  pending: Option<(u64, Instant)>,
+}
+
+impl Pulse {
+  pub(crate) fn new(heartbeat: Option<Heartbeat>) -> Self {
+    let interval: Option<(Delay, Duration)> = heartbeat
+      .map(|heartbeat| (Delay::new(heartbeat.interval), heartbeat.interval));
+
+    Self {
+      interval,
+      limit: heartbeat.map_or(0, |heartbeat| heartbeat.misses),
+      misses: 0,
+      sequence: 0,
+      pending: None,
+    }
+  }
+
+  /// Returns the time between pings.
+  pub(crate) fn period(&self) -> Duration {
+    self.interval.as_ref().map_or(Duration::ZERO, |(_, period)| *period)
+  }
+
+  /// Disable the heartbeat.
+  pub(crate) fn stop(&mut self) {
+    self.interval = None;
+    self.pending = None;
+  }
+
+  /// Waits for the next tick, or forever if the heartbeat is disabled.
+  pub(crate) async fn tick(&mut self) -> Beat {
+    match self.interval {
+      Some((ref mut delay, period)) => {
+        (&mut *delay).await;
+        // Ticks missed while the loop was busy are not made up for
+        delay.reset(period);
+      }
+      None => future::pending().await,
+    }
+
+    if self.pending.is_some() {
+      self.misses += 1;
+
+      if self.misses >= self.limit {
+        return Beat::Dead;
+      }
+    }
+
+    self.sequence = self.sequence.wrapping_add(1);
+    self.pending = Some((self.sequence, Instant::now()));
+
+    Beat::Ping(self.sequence.to_be_bytes().to_vec())
+  }
+
+  /// Handle an inbound frame of any kind, which proves the peer is alive.
+  #[inline]
+  pub(crate) fn alive(&mut self) {
+    self.misses = 0;
+  }
+
+  /// Handle a pong frame, updating `latency` if it answers the last ping.
+  pub(crate) fn pong(&mut self, payload: &[u8], latency: &Latency) {
+    let Some((sequence, sent)) = self.pending else {
+      return;
+    };
+
+    if payload == sequence.to_be_bytes() {
+      latency.set(sent.elapsed());
+      self.pending = None;
+    }
+  }
+}
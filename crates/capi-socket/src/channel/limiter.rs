@@ -0,0 +1,194 @@
+use capi_core::RequestType;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::channel::runtime;
+use crate::channel::ChannelConfig;
+
+// =============================================================================
+// Rate Limit
+// =============================================================================
+
+/// A token-bucket budget of `capacity` requests per `period`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+  capacity: u32,
+  period: Duration,
+}
+
+impl RateLimit {
+  /// Allow bursts of `capacity` requests, refilled over `period`.
+  ///
+  /// A `capacity` of zero is raised to one.
+  #[inline]
+  pub const fn new(capacity: u32, period: Duration) -> Self {
+    let capacity: u32 = if capacity == 0 { 1 } else { capacity };
+
+    Self { capacity, period }
+  }
+
+  /// Returns the maximum number of requests in a burst.
+  #[inline]
+  pub const fn capacity(&self) -> u32 {
+    self.capacity
+  }
+
+  /// Returns the time it takes to refill a full bucket.
+  #[inline]
+  pub const fn period(&self) -> Duration {
+    self.period
+  }
+}
+
+// =============================================================================
+// Token Bucket
+// =============================================================================
+
+#[derive(Debug)]
+struct Bucket {
+  limit: RateLimit,
+  tokens: f64,
+  update: Instant,
+  penalty: u32,
+}
+
+impl Bucket {
+  /// The maximum factor the refill rate is slowed down by.
+  const MAX_PENALTY: u32 = 8;
+
+  fn new(limit: RateLimit) -> Self {
+    Self {
+      limit,
+      tokens: f64::from(limit.capacity),
+      update: Instant::now(),
+      penalty: 1,
+    }
+  }
+
+  /// Returns the time it takes to refill a single token.
+  fn interval(&self) -> Duration {
+    let period: Duration = self.limit.period.saturating_mul(self.penalty);
+    period / self.limit.capacity
+  }
+
+  fn refill(&mut self, now: Instant) {
+    let elapsed: Duration = now.saturating_duration_since(self.update);
+    let interval: Duration = self.interval();
+
+    if !interval.is_zero() {
+      self.tokens += elapsed.as_secs_f64() / interval.as_secs_f64();
+      self.tokens = self.tokens.min(f64::from(self.limit.capacity));
+    } else {
+      self.tokens = f64::from(self.limit.capacity);
+    }
+
+    self.update = now;
+  }
+
+  /// Take a token, or return the time until one is available.
+  fn take(&mut self) -> Result<(), Duration> {
+    self.refill(Instant::now());
+
+    if self.tokens >= 1.0 {
+      self.tokens -= 1.0;
+      Ok(())
+    } else {
+      let wait: f64 = self.interval().as_secs_f64() * (1.0 - self.tokens);
+      Err(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+    }
+  }
+
+  /// Slow down after the server reported a rate limit.
+  fn penalize(&mut self) {
+    self.refill(Instant::now());
+    self.tokens = 0.0;
+    self.penalty = (self.penalty * 2).min(Self::MAX_PENALTY);
+  }
+
+  /// Speed back up after a request went through.
+  fn reward(&mut self) {
+    if self.penalty > 1 {
+      self.refill(Instant::now());
+      self.penalty -= 1;
+    }
+  }
+}
+
+// =============================================================================
+// Limiter
+// =============================================================================
+
+/// Client-side throttling of outgoing requests.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+  general: Option<Mutex<Bucket>>,
+  moderation: Option<Mutex<Bucket>>,
+}
+
+impl Limiter {
+  pub(crate) fn new(config: &ChannelConfig) -> Self {
+    Self {
+      general: config.rate_limit.map(Bucket::new).map(Mutex::new),
+      moderation: config.moderation_rate_limit.map(Bucket::new).map(Mutex::new),
+    }
+  }
+
+  /// Wait until a request of type `command` may be sent.
+  pub(crate) async fn acquire(&self, command: RequestType) {
+    while let Err(delay) = self.reserve(command) {
+      runtime::sleep(delay).await;
+    }
+  }
+
+  /// Take a token for a request of type `command` without waiting.
+  ///
+  /// Returns `false` if the budget is used up.
+  #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+  pub(crate) fn try_acquire(&self, command: RequestType) -> bool {
+    self.reserve(command).is_ok()
+  }
+
+  /// Take a token for a request of type `command`, or return the time until
+  /// one is available.
+  pub(crate) fn reserve(&self, command: RequestType) -> Result<(), Duration> {
+    self.bucket(command).map_or(Ok(()), |bucket| lock(bucket).take())
+  }
+
+  /// Adapt the budget of `command` to the response status.
+  pub(crate) fn update(&self, command: RequestType, rate_limited: bool) {
+    let Some(bucket) = self.bucket(command) else {
+      return;
+    };
+
+    if rate_limited {
+      lock(bucket).penalize();
+    } else {
+      lock(bucket).reward();
+    }
+  }
+
+  fn bucket(&self, command: RequestType) -> Option<&Mutex<Bucket>> {
+    if command.is_moderation() {
+      self.moderation.as_ref().or(self.general.as_ref())
+    } else {
+      self.general.as_ref()
+    }
+  }
+}
+
+impl From<RateLimit> for Limiter {
+  /// A single budget shared by all request types.
+  #[inline]
+  fn from(rate_limit: RateLimit) -> Self {
+    Self {
+      general: Some(Mutex::new(Bucket::new(rate_limit))),
+      moderation: None,
+    }
+  }
+}
+
+#[inline]
+fn lock(bucket: &Mutex<Bucket>) -> std::sync::MutexGuard<'_, Bucket> {
+  bucket.lock().unwrap_or_else(|error| error.into_inner())
+}
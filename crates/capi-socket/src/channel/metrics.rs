@@ -0,0 +1,144 @@
+use capi_core::EventType;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+
+use crate::channel::ChannelError;
+use crate::channel::Priority;
+
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+
+#[cfg(feature = "metrics")]
+use crate::channel::Recorder;
+
+// =============================================================================
+// Channel Metrics
+// =============================================================================
+
+/// The [`Recorder`] of a channel.
+///
+/// Does nothing unless the `metrics` feature is enabled.
+#[derive(Clone, Default)]
+pub(crate) struct Metrics {
+  #[cfg(feature = "metrics")]
+  recorder: Option<Arc<dyn Recorder>>,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+  #[inline]
+  pub(crate) const fn new() -> Self {
+    Self { recorder: None }
+  }
+
+  #[inline]
+  pub(crate) fn set(&mut self, recorder: Arc<dyn Recorder>) {
+    self.recorder = Some(recorder);
+  }
+
+  #[inline]
+  pub(crate) fn request(&self, command: RequestType) {
+    if let Some(ref recorder) = self.recorder {
+      recorder.request(command);
+    }
+  }
+
+  pub(crate) fn response(
+    &self,
+    command: RequestType,
+    latency: Duration,
+    output: &Result<ResponsePacket, ChannelError>,
+  ) {
+    let Some(ref recorder) = self.recorder else {
+      return;
+    };
+
+    match output {
+      Ok(response) => recorder.response(command, latency, response.status()),
+      Err(error) => match error.response_status() {
+        Some(status) => recorder.response(command, latency, Some(status)),
+        None => recorder.failure(command, error.kind()),
+      },
+    }
+  }
+
+  #[inline]
+  pub(crate) fn event(&self, event: EventType) {
+    if let Some(ref recorder) = self.recorder {
+      recorder.event(event);
+    }
+  }
+
+  #[inline]
+  pub(crate) fn error(&self, error: &ChannelError) {
+    if let Some(ref recorder) = self.recorder {
+      recorder.error(error.kind());
+    }
+  }
+
+  #[inline]
+  pub(crate) fn request_queue(&self, priority: Priority, depth: usize) {
+    if let Some(ref recorder) = self.recorder {
+      recorder.request_queue(priority, depth);
+    }
+  }
+
+  #[inline]
+  pub(crate) fn event_queue(&self, depth: usize) {
+    if let Some(ref recorder) = self.recorder {
+      recorder.event_queue(depth);
+    }
+  }
+
+  #[inline]
+  pub(crate) fn reconnect(&self) {
+    if let Some(ref recorder) = self.recorder {
+      recorder.reconnect();
+    }
+  }
+}
+
+#[cfg(not(feature = "metrics"))]
+impl Metrics {
+  #[inline]
+  pub(crate) const fn new() -> Self {
+    Self {}
+  }
+
+  #[inline]
+  pub(crate) fn request(&self, _command: RequestType) {}
+
+  #[inline]
+  pub(crate) fn response(
+    &self,
+    _command: RequestType,
+    _latency: Duration,
+    _output: &Result<ResponsePacket, ChannelError>,
+  ) {
+  }
+
+  #[inline]
+  pub(crate) fn event(&self, _event: EventType) {}
+
+  #[inline]
+  pub(crate) fn error(&self, _error: &ChannelError) {}
+
+  #[inline]
+  pub(crate) fn request_queue(&self, _priority: Priority, _depth: usize) {}
+
+  #[inline]
+  pub(crate) fn event_queue(&self, _depth: usize) {}
+
+  #[inline]
+  pub(crate) fn reconnect(&self) {}
+}
+
+impl Debug for Metrics {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Metrics").finish_non_exhaustive()
+  }
+}
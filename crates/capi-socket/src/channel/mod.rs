@@ -1,9 +1,73 @@
+#[allow(clippy::module_inception)]
 mod channel;
+mod config;
 mod error;
+mod heartbeat;
+pub(crate) mod limiter;
+mod metrics;
+mod priority;
+mod queue;
+pub(crate) mod runtime;
+mod shutdown;
+mod subscription;
+mod supervisor;
+mod trace;
 mod traits;
 
+feature! {
+  #[feature = "metrics"]
+  mod recorder;
+}
+
+feature! {
+  #[feature = "tower"]
+  mod service;
+}
+
 pub use self::channel::Channel;
+pub use self::channel::ChannelHandle;
+pub use self::channel::EventReceiver;
+pub use self::config::ChannelConfig;
 pub use self::error::ChannelError;
 pub use self::error::ErrorKind;
+pub use self::heartbeat::Heartbeat;
+pub use self::limiter::RateLimit;
+pub use self::priority::Priority;
+pub use self::queue::Overflow;
+pub use self::runtime::JoinError;
+pub use self::runtime::JoinHandle;
+pub use self::runtime::Spawner;
+pub use self::shutdown::Shutdown;
+pub use self::shutdown::ShutdownSummary;
+pub use self::subscription::Filter;
+pub use self::subscription::Subscription;
+pub use self::supervisor::Backoff;
+pub use self::supervisor::Connector;
+pub use self::supervisor::Notification;
+pub use self::supervisor::Supervisor;
+pub use self::traits::CloseFrame;
+pub use self::traits::Control;
+pub use self::traits::Frame;
+pub use self::traits::LocalTransport;
 pub use self::traits::Message;
+pub use self::traits::NotData;
 pub use self::traits::Transport;
+
+feature! {
+  #[feature = "tokio"]
+  pub use self::runtime::TokioSpawner;
+}
+
+feature! {
+  #[feature = "metrics"]
+  pub use self::recorder::Histogram;
+  pub use self::recorder::MemoryRecorder;
+  pub use self::recorder::Recorder;
+  pub use self::recorder::Snapshot;
+}
+
+feature! {
+  #[feature = "tower"]
+  pub use self::service::ChannelService;
+  pub use self::service::ServiceFuture;
+}
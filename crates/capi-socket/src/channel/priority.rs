@@ -0,0 +1,47 @@
+use capi_core::RequestType;
+
+// =============================================================================
+// Request Priority
+// =============================================================================
+
+/// The order in which queued requests are written to the transport.
+///
+/// Whenever requests of several priorities are queued, the one with the
+/// highest priority is sent first. Requests of the same priority are sent in
+/// the order they were queued, which is the call order for requests issued
+/// from a single task.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+  /// Moderation commands (ban, kick, unban, set moderator).
+  Moderation,
+  /// Chat messages and session commands.
+  Chat,
+  /// Announcements and other bulk traffic.
+  ///
+  /// No request type defaults to this priority, see
+  /// [`ChannelHandle::with_priority`][crate::channel::ChannelHandle::with_priority].
+  Bulk,
+}
+
+impl Priority {
+  pub(crate) const COUNT: usize = 3;
+
+  /// Returns the default priority of the given request type.
+  #[inline]
+  pub const fn of(command: RequestType) -> Self {
+    if command.is_moderation() {
+      Self::Moderation
+    } else {
+      Self::Chat
+    }
+  }
+
+  #[inline]
+  pub(crate) const fn index(&self) -> usize {
+    match self {
+      Self::Moderation => 0,
+      Self::Chat => 1,
+      Self::Bulk => 2,
+    }
+  }
+}
@@ -0,0 +1,208 @@
+use futures_util::task::AtomicWaker;
+use futures_util::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::task::Context;
+use std::task::Poll;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::Notify;
+
+// =============================================================================
+// Overflow Policy
+// =============================================================================
+
+/// What to do with an event when the event queue is full.
+///
+/// Responses are delivered separately from events and are never held back by
+/// a full event queue.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Overflow {
+  /// Wait for the consumer to catch up.
+  ///
+  /// Events that do not fit are held in memory until there is room, the
+  /// connection keeps being read meanwhile.
+  #[default]
+  Block,
+  /// Discard the oldest queued event to make room.
+  DropOldest,
+  /// Discard the incoming event.
+  DropNewest,
+  /// Stop the channel with an [`Overflow`][crate::channel::ErrorKind::Overflow] error.
+  Error,
+}
+
+// =============================================================================
+// Event Queue
+// =============================================================================
+
+struct State<T> {
+  items: VecDeque<T>,
+  sender: bool,
+  receiver: bool,
+}
+
+struct Shared<T> {
+  state: Mutex<State<T>>,
+  capacity: usize,
+  waker: AtomicWaker,
+  space: Notify,
+}
+
+impl<T> Shared<T> {
+  fn lock(&self) -> MutexGuard<'_, State<T>> {
+    self.state.lock().unwrap_or_else(|error| error.into_inner())
+  }
+}
+
+/// Creates a bounded single-producer, single-consumer queue.
+pub(crate) fn queue<T>(capacity: usize) -> (QueueSend<T>, QueueRecv<T>) {
+  let shared: Arc<Shared<T>> = Arc::new(Shared {
+    state: Mutex::new(State {
+      items: VecDeque::with_capacity(capacity),
+      sender: true,
+      receiver: true,
+    }),
+    capacity: capacity.max(1),
+    waker: AtomicWaker::new(),
+    space: Notify::new(),
+  });
+
+  (
+    QueueSend {
+      shared: Arc::clone(&shared),
+    },
+    QueueRecv { shared },
+  )
+}
+
+// =============================================================================
+// Queue Sender
+// =============================================================================
+
+pub(crate) struct QueueSend<T> {
+  shared: Arc<Shared<T>>,
+}
+
+impl<T> QueueSend<T> {
+  /// Returns the number of queued items.
+  #[inline]
+  pub(crate) fn len(&self) -> usize {
+    self.shared.lock().items.len()
+  }
+
+  /// Push `item` if there is room, returns it otherwise.
+  ///
+  /// Items are discarded if the receiver is gone.
+  pub(crate) fn try_push(&self, item: T) -> Result<(), T> {
+    let mut state: MutexGuard<'_, State<T>> = self.shared.lock();
+
+    if !state.receiver {
+      return Ok(());
+    }
+
+    if state.items.len() >= self.shared.capacity {
+      return Err(item);
+    }
+
+    state.items.push_back(item);
+    drop(state);
+
+    self.shared.waker.wake();
+
+    Ok(())
+  }
+
+  /// Push `item`, discarding the oldest item if the queue is full.
+  pub(crate) fn force_push(&self, item: T) {
+    let mut state: MutexGuard<'_, State<T>> = self.shared.lock();
+
+    if !state.receiver {
+      return;
+    }
+
+    if state.items.len() >= self.shared.capacity {
+      state.items.pop_front();
+    }
+
+    state.items.push_back(item);
+    drop(state);
+
+    self.shared.waker.wake();
+  }
+
+  /// Waits until there is room in the queue or the receiver is gone.
+  pub(crate) async fn ready(&self) {
+    loop {
+      {
+        let state: MutexGuard<'_, State<T>> = self.shared.lock();
+
+        if !state.receiver || state.items.len() < self.shared.capacity {
+          return;
+        }
+      }
+
+      self.shared.space.notified().await;
+    }
+  }
+}
+
+impl<T> Drop for QueueSend<T> {
+  fn drop(&mut self) {
+    self.shared.lock().sender = false;
+    self.shared.waker.wake();
+  }
+}
+
+// =============================================================================
+// Queue Receiver
+// =============================================================================
+
+pub(crate) struct QueueRecv<T> {
+  shared: Arc<Shared<T>>,
+}
+
+impl<T> QueueRecv<T> {
+  pub(crate) fn try_recv(&mut self) -> Result<T, TryRecvError> {
+    let mut state: MutexGuard<'_, State<T>> = self.shared.lock();
+
+    match state.items.pop_front() {
+      Some(item) => {
+        drop(state);
+        self.shared.space.notify_one();
+        Ok(item)
+      }
+      None if state.sender => Err(TryRecvError::Empty),
+      None => Err(TryRecvError::Disconnected),
+    }
+  }
+}
+
+impl<T> Stream for QueueRecv<T> {
+  type Item = T;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    // Register before checking so a push in between is not missed
+    self.shared.waker.register(cx.waker());
+
+    match self.try_recv() {
+      Ok(item) => Poll::Ready(Some(item)),
+      Err(TryRecvError::Empty) => Poll::Pending,
+      Err(TryRecvError::Disconnected) => Poll::Ready(None),
+    }
+  }
+}
+
+impl<T> Drop for QueueRecv<T> {
+  fn drop(&mut self) {
+    let mut state: MutexGuard<'_, State<T>> = self.shared.lock();
+
+    state.receiver = false;
+    state.items.clear();
+    drop(state);
+
+    self.shared.space.notify_one();
+  }
+}
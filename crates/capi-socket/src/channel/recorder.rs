@@ -0,0 +1,219 @@
+use capi_core::EventType;
+use capi_core::RequestType;
+use capi_core::ResponseStatus;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+
+use crate::channel::ErrorKind;
+use crate::channel::Priority;
+
+// =============================================================================
+// Metrics Recorder
+// =============================================================================
+
+/// Receives measurements from a [`Channel`][crate::channel::Channel].
+///
+/// All methods do nothing by default.
+pub trait Recorder: Send + Sync + 'static {
+  /// A request was sent.
+  fn request(&self, _command: RequestType) {}
+
+  /// A response was received `latency` after the request was sent.
+  ///
+  /// Time spent waiting for the client-side [`RateLimit`][crate::channel::RateLimit]
+  /// is not included.
+  fn response(&self, _command: RequestType, _latency: Duration, _status: Option<ResponseStatus>) {}
+
+  /// A request ended without a response (e.g. it timed out).
+  fn failure(&self, _command: RequestType, _kind: ErrorKind) {}
+
+  /// An event was received.
+  fn event(&self, _event: EventType) {}
+
+  /// The run loop published an error to the event stream.
+  fn error(&self, _kind: ErrorKind) {}
+
+  /// The number of requests queued at `priority` after queueing a request.
+  fn request_queue(&self, _priority: Priority, _depth: usize) {}
+
+  /// The number of events queued after queueing an event.
+  fn event_queue(&self, _depth: usize) {}
+
+  /// A [`Supervisor`][crate::channel::Supervisor] re-opened the connection.
+  fn reconnect(&self) {}
+}
+
+// =============================================================================
+// Latency Histogram
+// =============================================================================
+
+const BUCKETS: usize = Histogram::BOUNDS.len() + 1;
+
+/// A histogram of response latencies.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Histogram {
+  counts: [u64; BUCKETS],
+  count: u64,
+  sum: Duration,
+}
+
+impl Histogram {
+  /// The upper bounds of the histogram buckets.
+  pub const BOUNDS: [Duration; 12] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+  ];
+
+  fn record(&mut self, latency: Duration) {
+    let index: usize = Self::BOUNDS.partition_point(|bound| *bound < latency);
+
+    self.counts[index] += 1;
+    self.count += 1;
+    self.sum = self.sum.saturating_add(latency);
+  }
+
+  /// Returns the number of recorded latencies.
+  #[inline]
+  pub const fn count(&self) -> u64 {
+    self.count
+  }
+
+  /// Returns the sum of all recorded latencies.
+  #[inline]
+  pub const fn sum(&self) -> Duration {
+    self.sum
+  }
+
+  /// Returns the mean latency, if any was recorded.
+  pub fn mean(&self) -> Option<Duration> {
+    let count: u32 = u32::try_from(self.count).unwrap_or(u32::MAX);
+
+    if count == 0 {
+      None
+    } else {
+      Some(self.sum / count)
+    }
+  }
+
+  /// Returns the upper bound and count of each bucket.
+  ///
+  /// The last bucket has no upper bound.
+  pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+    Self::BOUNDS
+      .iter()
+      .copied()
+      .map(Some)
+      .chain([None])
+      .zip(self.counts.iter().copied())
+  }
+}
+
+// =============================================================================
+// Metrics Snapshot
+// =============================================================================
+
+/// The measurements collected by a [`MemoryRecorder`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+  /// Requests sent, by command.
+  pub requests: BTreeMap<RequestType, u64>,
+  /// Response latency, by command.
+  pub latency: BTreeMap<RequestType, Histogram>,
+  /// Responses received, by status `(area, code)`.
+  pub statuses: BTreeMap<(u8, u8), u64>,
+  /// Requests that ended without a response, by error kind.
+  pub failures: BTreeMap<ErrorKind, u64>,
+  /// Events received, by type.
+  pub events: BTreeMap<EventType, u64>,
+  /// Errors published by the run loop, by kind.
+  pub errors: BTreeMap<ErrorKind, u64>,
+  /// The last observed request queue depth, by priority.
+  pub request_queue: BTreeMap<Priority, usize>,
+  /// The last observed event queue depth.
+  pub event_queue: usize,
+  /// Connections re-opened by a supervisor.
+  pub reconnects: u64,
+}
+
+// =============================================================================
+// Memory Recorder
+// =============================================================================
+
+/// A [`Recorder`] that keeps counters in memory.
+#[derive(Debug, Default)]
+pub struct MemoryRecorder {
+  inner: Mutex<Snapshot>,
+}
+
+impl MemoryRecorder {
+  #[inline]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns a copy of the current measurements.
+  #[inline]
+  pub fn snapshot(&self) -> Snapshot {
+    self.lock().clone()
+  }
+
+  /// Clears all measurements.
+  #[inline]
+  pub fn reset(&self) {
+    *self.lock() = Snapshot::default();
+  }
+
+  fn lock(&self) -> MutexGuard<'_, Snapshot> {
+    self.inner.lock().unwrap_or_else(|error| error.into_inner())
+  }
+}
+
+impl Recorder for MemoryRecorder {
+  fn request(&self, command: RequestType) {
+    *self.lock().requests.entry(command).or_default() += 1;
+  }
+
+  fn response(&self, command: RequestType, latency: Duration, status: Option<ResponseStatus>) {
+    let mut inner: MutexGuard<'_, Snapshot> = self.lock();
+    let status: (u8, u8) = status.map_or((0, 0), |status| (status.area(), status.code()));
+
+    inner.latency.entry(command).or_default().record(latency);
+    *inner.statuses.entry(status).or_default() += 1;
+  }
+
+  fn failure(&self, _command: RequestType, kind: ErrorKind) {
+    *self.lock().failures.entry(kind).or_default() += 1;
+  }
+
+  fn event(&self, event: EventType) {
+    *self.lock().events.entry(event).or_default() += 1;
+  }
+
+  fn error(&self, kind: ErrorKind) {
+    *self.lock().errors.entry(kind).or_default() += 1;
+  }
+
+  fn request_queue(&self, priority: Priority, depth: usize) {
+    self.lock().request_queue.insert(priority, depth);
+  }
+
+  fn event_queue(&self, depth: usize) {
+    self.lock().event_queue = depth;
+  }
+
+  fn reconnect(&self) {
+    self.lock().reconnects += 1;
+  }
+}
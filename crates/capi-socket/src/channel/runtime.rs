@@ -0,0 +1,280 @@
+use futures_timer::Delay;
+use futures_util::future::abortable;
+use futures_util::future::select;
+use futures_util::future::AbortHandle;
+use futures_util::future::BoxFuture;
+use futures_util::future::Either;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::pin::pin;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::channel::ChannelError;
+use crate::channel::ErrorKind;
+
+// =============================================================================
+// Spawner
+// =============================================================================
+
+/// Runs the background tasks of a [`Channel`][crate::channel::Channel].
+///
+/// Implemented for closures, so any executor can be plugged in:
+///
+/// ```ignore
+/// let config = ChannelConfig::new().spawner(Arc::new(|future| {
+///   async_std::task::spawn(future);
+/// }));
+/// ```
+pub trait Spawner: Send + Sync + 'static {
+  /// Run `future` to completion in the background.
+  fn spawn(&self, future: BoxFuture<'static, ()>);
+}
+
+impl<F> Spawner for F
+where
+  F: Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+{
+  #[inline]
+  fn spawn(&self, future: BoxFuture<'static, ()>) {
+    self(future);
+  }
+}
+
+/// A [`Spawner`] running tasks on the current tokio runtime.
+///
+/// This is the default spawner of a channel. Spawning outside a tokio runtime
+/// panics.
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioSpawner;
+
+#[cfg(feature = "tokio")]
+impl Spawner for TokioSpawner {
+  #[inline]
+  fn spawn(&self, future: BoxFuture<'static, ()>) {
+    tokio::spawn(future);
+  }
+}
+
+/// The configured [`Spawner`] of a channel.
+#[derive(Clone, Default)]
+pub(crate) struct Runtime {
+  spawner: Option<Arc<dyn Spawner>>,
+}
+
+impl Runtime {
+  #[inline]
+  pub(crate) const fn new() -> Self {
+    Self { spawner: None }
+  }
+
+  #[inline]
+  pub(crate) fn set(&mut self, spawner: Arc<dyn Spawner>) {
+    self.spawner = Some(spawner);
+  }
+
+  /// Returns the configured spawner, or [`TokioSpawner`] if none is
+  /// configured and the `tokio` feature is enabled.
+  pub(crate) fn spawner(&self) -> Option<Arc<dyn Spawner>> {
+    match self.spawner {
+      Some(ref spawner) => Some(Arc::clone(spawner)),
+      #[cfg(feature = "tokio")]
+      None => Some(Arc::new(TokioSpawner)),
+      #[cfg(not(feature = "tokio"))]
+      None => None,
+    }
+  }
+
+  /// Run `future` in the background, returning a handle to its output.
+  ///
+  /// Fails if there is no [`spawner`][Self::spawner].
+  pub(crate) fn spawn<F>(&self, future: F) -> Result<JoinHandle<F::Output>, ChannelError>
+  where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+  {
+    let Some(spawner) = self.spawner() else {
+      return Err(ChannelError::msg(
+        ErrorKind::Spawn,
+        "no spawner configured, see `ChannelConfig::spawner`",
+      ));
+    };
+
+    let (task, handle) = task(future);
+
+    spawner.spawn(Box::pin(task));
+
+    Ok(handle)
+  }
+}
+
+impl Debug for Runtime {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Runtime")
+      .field("spawner", &self.spawner.as_ref().map(|_| "dyn Spawner"))
+      .finish()
+  }
+}
+
+/// Wrap `future` in a task reporting its output to the returned handle.
+///
+/// The task does nothing until it is polled.
+pub(crate) fn task<F>(future: F) -> (impl Future<Output = ()>, JoinHandle<F::Output>)
+where
+  F: Future + 'static,
+{
+  let (send, recv) = oneshot::channel();
+  let (future, abort) = abortable(future);
+  let finished: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+  let guard: Finished = Finished(Arc::clone(&finished));
+
+  let task = async move {
+    let _guard: Finished = guard;
+
+    if let Ok(output) = future.await {
+      // The handle may have been dropped.
+      let _ignore = send.send(output);
+    }
+  };
+
+  let handle: JoinHandle<F::Output> = JoinHandle {
+    recv,
+    abort,
+    finished,
+  };
+
+  (task, handle)
+}
+
+/// Marks a task as finished when dropped, i.e. completed, aborted, or
+/// discarded by the executor.
+struct Finished(Arc<AtomicBool>);
+
+impl Drop for Finished {
+  #[inline]
+  fn drop(&mut self) {
+    self.0.store(true, Ordering::Release);
+  }
+}
+
+// =============================================================================
+// Join Handle
+// =============================================================================
+
+/// An owned handle to a background task.
+///
+/// Awaiting the handle waits for the output of the task. Dropping the handle
+/// detaches the task, it keeps running.
+pub struct JoinHandle<T> {
+  recv: oneshot::Receiver<T>,
+  abort: AbortHandle,
+  finished: Arc<AtomicBool>,
+}
+
+impl<T> JoinHandle<T> {
+  /// Cancel the task. It stops the next time it yields.
+  #[inline]
+  pub fn abort(&self) {
+    self.abort.abort();
+  }
+
+  /// Returns `true` if the task has finished.
+  #[inline]
+  pub fn is_finished(&self) -> bool {
+    self.finished.load(Ordering::Acquire)
+  }
+}
+
+impl<T> Debug for JoinHandle<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("JoinHandle")
+      .field("finished", &self.is_finished())
+      .finish_non_exhaustive()
+  }
+}
+
+impl<T> Future for JoinHandle<T> {
+  type Output = Result<T, JoinError>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    Pin::new(&mut self.recv).poll(cx).map(|output| {
+      output.map_err(|_| JoinError {
+        cancelled: self.abort.is_aborted(),
+      })
+    })
+  }
+}
+
+// =============================================================================
+// Join Error
+// =============================================================================
+
+/// A background task did not run to completion.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct JoinError {
+  cancelled: bool,
+}
+
+impl JoinError {
+  /// Returns `true` if the task was [`aborted`][JoinHandle::abort].
+  ///
+  /// Otherwise, the task panicked or was dropped by its executor.
+  #[inline]
+  pub const fn is_cancelled(&self) -> bool {
+    self.cancelled
+  }
+}
+
+impl Display for JoinError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    if self.cancelled {
+      f.write_str("task was cancelled")
+    } else {
+      f.write_str("task panicked or was dropped by its executor")
+    }
+  }
+}
+
+impl Error for JoinError {}
+
+// =============================================================================
+// Timers
+// =============================================================================
+
+/// A deadline passed before the operation completed.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct Elapsed;
+
+impl Display for Elapsed {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.write_str("deadline has elapsed")
+  }
+}
+
+impl Error for Elapsed {}
+
+/// Waits until `duration` has elapsed.
+#[inline]
+pub(crate) async fn sleep(duration: Duration) {
+  Delay::new(duration).await;
+}
+
+/// Waits for `future` to complete, at most for `duration`.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+  match select(pin!(future), Delay::new(duration)).await {
+    Either::Left((output, _)) => Ok(output),
+    Either::Right(((), _)) => Err(Elapsed),
+  }
+}
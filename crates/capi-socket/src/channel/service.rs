@@ -0,0 +1,119 @@
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::ready;
+use std::task::Poll;
+use tower_service::Service;
+
+use crate::channel::channel::Reserved;
+use crate::channel::ChannelError;
+use crate::channel::ChannelHandle;
+use crate::channel::Transport;
+
+type Ready<T> = Pin<Box<dyn Future<Output = Result<Reserved<T>, ChannelError>> + Send>>;
+
+/// The future returned by [`ChannelService`].
+pub type ServiceFuture = Pin<Box<dyn Future<Output = Result<ResponsePacket, ChannelError>> + Send>>;
+
+// =============================================================================
+// Channel Service
+// =============================================================================
+
+/// A [`tower`](https://docs.rs/tower) service sending requests through a
+/// [`ChannelHandle`].
+///
+/// Readiness reserves a slot in the request queue of the handle's
+/// [`priority`][ChannelHandle::with_priority], or the
+/// [`Chat`][crate::channel::Priority::Chat] queue if the handle has none. The
+/// next call is queued in that slot; a request of another priority (e.g. a
+/// moderation command on a default handle) waits for room in its own queue.
+///
+/// Requests use the priority of the handle and the
+/// [`timeout`][crate::channel::ChannelConfig::timeout] of the channel.
+pub struct ChannelService<T: Transport> {
+  handle: ChannelHandle<T>,
+  ready: Option<Ready<T>>,
+  reserved: Option<Reserved<T>>,
+}
+
+impl<T: Transport> ChannelService<T> {
+  /// Create a new `ChannelService` sending requests through `handle`.
+  #[inline]
+  pub const fn new(handle: ChannelHandle<T>) -> Self {
+    Self {
+      handle,
+      ready: None,
+      reserved: None,
+    }
+  }
+
+  /// Returns a reference to the channel handle.
+  #[inline]
+  pub const fn handle(&self) -> &ChannelHandle<T> {
+    &self.handle
+  }
+
+  /// Consumes the service, returning the channel handle.
+  #[inline]
+  pub fn into_inner(self) -> ChannelHandle<T> {
+    self.handle
+  }
+}
+
+impl<T: Transport> Clone for ChannelService<T> {
+  #[inline]
+  fn clone(&self) -> Self {
+    Self::new(self.handle.clone())
+  }
+}
+
+impl<T: Transport> Debug for ChannelService<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("ChannelService")
+      .field("handle", &self.handle)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<T: Transport> From<ChannelHandle<T>> for ChannelService<T> {
+  #[inline]
+  fn from(other: ChannelHandle<T>) -> Self {
+    Self::new(other)
+  }
+}
+
+impl<T: Transport> Service<RequestPacket> for ChannelService<T> {
+  type Response = ResponsePacket;
+  type Error = ChannelError;
+  type Future = ServiceFuture;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    if self.reserved.is_some() {
+      return Poll::Ready(Ok(()));
+    }
+
+    let ready: &mut Ready<T> = self
+      .ready
+      .get_or_insert_with(|| Box::pin(self.handle.reserve()));
+
+    let output: Result<Reserved<T>, ChannelError> = ready!(ready.as_mut().poll(cx));
+
+    self.ready = None;
+
+    Poll::Ready(output.map(|reserved| {
+      self.reserved = Some(reserved);
+    }))
+  }
+
+  fn call(&mut self, request: RequestPacket) -> Self::Future {
+    let handle: ChannelHandle<T> = self.handle.clone();
+    let reserved: Option<Reserved<T>> = self.reserved.take();
+
+    Box::pin(async move { handle.send_reserved(request, reserved).await })
+  }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+// =============================================================================
+// Shutdown Options
+// =============================================================================
+
+/// Options for a graceful shutdown of a [`Channel`][crate::channel::Channel].
+///
+/// A graceful shutdown runs in steps:
+///
+/// 1. Requests queued so far are sent, new requests made through any handle
+///    are rejected with a [`Closed`][crate::channel::ErrorKind::Closed] error.
+/// 2. Outstanding requests are given until the deadline to be answered.
+/// 3. If enabled, [`ChatDisconnect`][capi_core::packet::ChatDisconnect] is
+///    sent and the matching event awaited until the deadline.
+/// 4. The connection is closed with a normal close code.
+///
+/// The summary is reported even if the connection fails part way through.
+#[derive(Clone, Copy, Debug)]
+pub struct Shutdown {
+  pub(crate) deadline: Duration,
+  pub(crate) disconnect: bool,
+}
+
+impl Shutdown {
+  /// The default time to wait at each step.
+  pub const DEFAULT_DEADLINE: Duration = Duration::from_secs(5);
+
+  #[inline]
+  pub const fn new() -> Self {
+    Self {
+      deadline: Self::DEFAULT_DEADLINE,
+      disconnect: false,
+    }
+  }
+
+  /// Set the time to wait for outstanding responses, and for the disconnect
+  /// event if enabled.
+  ///
+  /// Deadlines too far in the future to represent (e.g. [`Duration::MAX`])
+  /// wait indefinitely.
+  #[inline]
+  pub const fn deadline(mut self, deadline: Duration) -> Self {
+    self.deadline = deadline;
+    self
+  }
+
+  /// Leave chat before closing the connection.
+  #[inline]
+  pub const fn disconnect(mut self, disconnect: bool) -> Self {
+    self.disconnect = disconnect;
+    self
+  }
+}
+
+impl Default for Shutdown {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// =============================================================================
+// Shutdown Summary
+// =============================================================================
+
+/// The outcome of a graceful [`Shutdown`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct ShutdownSummary {
+  pub(crate) completed: usize,
+  pub(crate) abandoned: usize,
+  pub(crate) disconnected: bool,
+}
+
+impl ShutdownSummary {
+  /// Returns the number of outstanding requests answered before the deadline.
+  #[inline]
+  pub const fn completed(&self) -> usize {
+    self.completed
+  }
+
+  /// Returns the number of requests rejected or left without a response.
+  #[inline]
+  pub const fn abandoned(&self) -> usize {
+    self.abandoned
+  }
+
+  /// Returns `true` if the bot left chat before the connection was closed.
+  #[inline]
+  pub const fn disconnected(&self) -> bool {
+    self.disconnected
+  }
+}
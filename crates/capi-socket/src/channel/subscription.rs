@@ -0,0 +1,181 @@
+use capi_core::EventPacket;
+use capi_core::EventType;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::channel::ChannelError;
+
+/// An event shared between all subscribers.
+pub(crate) type Fanout = Arc<Result<EventPacket, ChannelError>>;
+
+pub(crate) type FanoutRecv = broadcast::Receiver<Fanout>;
+pub(crate) type FanoutSend = broadcast::Sender<Fanout>;
+
+// =============================================================================
+// Event Filter
+// =============================================================================
+
+type Predicate = dyn Fn(&EventPacket) -> bool + Send + Sync;
+
+/// Selects the events delivered to a [`Subscription`].
+#[derive(Clone)]
+pub struct Filter {
+  inner: FilterKind,
+}
+
+#[derive(Clone)]
+enum FilterKind {
+  All,
+  Types(Vec<EventType>),
+  Predicate(Arc<Predicate>),
+}
+
+impl Filter {
+  /// Accept every event.
+  #[inline]
+  pub const fn all() -> Self {
+    Self {
+      inner: FilterKind::All,
+    }
+  }
+
+  /// Accept events of the given type.
+  #[inline]
+  pub fn event(event: EventType) -> Self {
+    Self::events([event])
+  }
+
+  /// Accept events of any of the given types.
+  #[inline]
+  pub fn events(events: impl IntoIterator<Item = EventType>) -> Self {
+    Self {
+      inner: FilterKind::Types(events.into_iter().collect()),
+    }
+  }
+
+  /// Accept events for which `predicate` returns `true`.
+  #[inline]
+  pub fn predicate<F>(predicate: F) -> Self
+  where
+    F: Fn(&EventPacket) -> bool + Send + Sync + 'static,
+  {
+    Self {
+      inner: FilterKind::Predicate(Arc::new(predicate)),
+    }
+  }
+
+  /// Returns `true` if the filter accepts `event`.
+  pub fn matches(&self, event: &EventPacket) -> bool {
+    match self.inner {
+      FilterKind::All => true,
+      FilterKind::Types(ref types) => types.contains(&event.command()),
+      FilterKind::Predicate(ref predicate) => predicate(event),
+    }
+  }
+}
+
+impl Debug for Filter {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match self.inner {
+      FilterKind::All => f.write_str("Filter::All"),
+      FilterKind::Types(ref types) => f.debug_tuple("Filter::Types").field(types).finish(),
+      FilterKind::Predicate(_) => f.write_str("Filter::Predicate(..)"),
+    }
+  }
+}
+
+impl Default for Filter {
+  #[inline]
+  fn default() -> Self {
+    Self::all()
+  }
+}
+
+// =============================================================================
+// Subscription
+// =============================================================================
+
+/// A [`stream`][Stream] of [`events`][EventPacket] matching a [`Filter`].
+///
+/// Every subscription receives its own copy of the event feed. A subscriber
+/// that falls behind skips the oldest events and receives an error of kind
+/// [`Lagged`][crate::channel::ErrorKind::Lagged] reporting how many were
+/// missed.
+pub struct Subscription {
+  recv: Option<BroadcastStream<Fanout>>,
+  filter: Filter,
+}
+
+impl Subscription {
+  #[inline]
+  pub(crate) fn new(recv: Option<FanoutRecv>, filter: Filter) -> Self {
+    Self {
+      recv: recv.map(BroadcastStream::new),
+      filter,
+    }
+  }
+
+  /// Returns a reference to the subscription filter.
+  #[inline]
+  pub const fn filter(&self) -> &Filter {
+    &self.filter
+  }
+}
+
+impl Debug for Subscription {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Subscription")
+      .field("filter", &self.filter)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Stream for Subscription {
+  type Item = Result<EventPacket, ChannelError>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this: &mut Self = &mut self;
+
+    let Some(recv) = this.recv.as_mut() else {
+      return Poll::Ready(None);
+    };
+
+    loop {
+      let item: Fanout = match recv.poll_next_unpin(cx) {
+        Poll::Ready(Some(Ok(item))) => item,
+        Poll::Ready(Some(Err(BroadcastStreamRecvError::Lagged(missed)))) => {
+          return Poll::Ready(Some(Err(ChannelError::lagged(missed))));
+        }
+        Poll::Ready(None) => {
+          this.recv = None;
+          return Poll::Ready(None);
+        }
+        Poll::Pending => {
+          return Poll::Pending;
+        }
+      };
+
+      match *item {
+        Ok(ref event) if this.filter.matches(event) => {
+          return Poll::Ready(Some(Ok(event.clone())));
+        }
+        Ok(_) => {
+          continue;
+        }
+        Err(ref error) => {
+          return Poll::Ready(Some(Err(error.replicate())));
+        }
+      }
+    }
+  }
+}
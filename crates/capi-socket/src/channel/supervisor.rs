@@ -0,0 +1,476 @@
+use capi_core::packet::Authenticate;
+use capi_core::packet::ChatConnect;
+use capi_core::EventPacket;
+use capi_core::IntoPayload;
+use capi_core::Packet;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::sync::RwLockWriteGuard;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::channel::Channel;
+use crate::channel::ChannelError;
+use crate::channel::ChannelHandle;
+use crate::channel::ErrorKind;
+use crate::channel::JoinHandle;
+use crate::channel::Transport;
+use crate::channel::runtime;
+use crate::channel::runtime::Runtime;
+use crate::middleware::Handler;
+use crate::socket::Socket;
+use crate::socket::SocketResponse;
+
+// =============================================================================
+// Connector
+// =============================================================================
+
+/// Opens new [`channels`][Channel] on behalf of a [`Supervisor`].
+pub trait Connector: Send + Sync + 'static {
+  type Transport: Transport;
+
+  // Note: async fn in trait does not currently apply `Send` bound.
+  fn connect(
+    &self,
+  ) -> impl Future<Output = Result<Channel<Self::Transport>, ChannelError>> + Send + '_;
+}
+
+// =============================================================================
+// Reconnect Backoff
+// =============================================================================
+
+/// Jittered exponential backoff between reconnect attempts.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+  initial: Duration,
+  maximum: Duration,
+  factor: u32,
+  attempts: Option<u32>,
+  jitter: bool,
+}
+
+impl Backoff {
+  #[inline]
+  pub const fn new() -> Self {
+    Self {
+      initial: Duration::from_secs(1),
+      maximum: Duration::from_secs(60),
+      factor: 2,
+      attempts: None,
+      jitter: true,
+    }
+  }
+
+  /// Set the delay before the first reconnect attempt.
+  #[inline]
+  pub const fn initial(mut self, initial: Duration) -> Self {
+    self.initial = initial;
+    self
+  }
+
+  /// Set the upper bound of the delay between attempts.
+  #[inline]
+  pub const fn maximum(mut self, maximum: Duration) -> Self {
+    self.maximum = maximum;
+    self
+  }
+
+  /// Set the factor the delay grows by after each failed attempt.
+  #[inline]
+  pub const fn factor(mut self, factor: u32) -> Self {
+    self.factor = factor;
+    self
+  }
+
+  /// Set the number of attempts before giving up, `None` retries forever.
+  #[inline]
+  pub const fn attempts(mut self, attempts: Option<u32>) -> Self {
+    self.attempts = attempts;
+    self
+  }
+
+  /// Enable or disable randomized delays.
+  #[inline]
+  pub const fn jitter(mut self, jitter: bool) -> Self {
+    self.jitter = jitter;
+    self
+  }
+
+  /// Returns the delay before the given (1-based) reconnect `attempt`.
+  pub fn delay(&self, attempt: u32) -> Duration {
+    let scale: u32 = self.factor.saturating_pow(attempt.saturating_sub(1));
+    let delay: Duration = self.initial.saturating_mul(scale).min(self.maximum);
+
+    if !self.jitter {
+      return delay;
+    }
+
+    // "Equal jitter": keep half of the delay, randomize the other half.
+    let random: u64 = RandomState::new().hash_one(attempt);
+    let random: f64 = random as f64 / u64::MAX as f64;
+
+    delay / 2 + (delay / 2).mul_f64(random)
+  }
+
+  #[inline]
+  const fn exhausted(&self, attempt: u32) -> bool {
+    match self.attempts {
+      Some(attempts) => attempt > attempts,
+      None => false,
+    }
+  }
+}
+
+impl Default for Backoff {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// =============================================================================
+// Supervisor Notification
+// =============================================================================
+
+/// An item of the [`Supervisor`] event stream.
+#[derive(Clone, Debug)]
+pub enum Notification {
+  /// An event received from the server.
+  Event(EventPacket),
+  /// The connection was lost, a reconnect is scheduled after `delay`.
+  Reconnecting { attempt: u32, delay: Duration },
+  /// The connection was re-opened and the session restored.
+  Restored,
+  /// The reconnect attempts were exhausted, no more items will follow.
+  GaveUp,
+}
+
+// =============================================================================
+// Supervisor State
+// =============================================================================
+
+struct Shared<T: Transport> {
+  handle: RwLock<Option<ChannelHandle<T>>>,
+  in_chat: AtomicBool,
+  stopping: AtomicBool,
+}
+
+impl<T: Transport> Shared<T> {
+  fn handle(&self) -> Result<ChannelHandle<T>, ChannelError> {
+    static ERR: &str = "connection is being restored";
+
+    self
+      .handle
+      .read()
+      .unwrap_or_else(|error| error.into_inner())
+      .clone()
+      .ok_or_else(|| ChannelError::msg(ErrorKind::Closed, ERR))
+  }
+
+  fn clear_handle(&self) {
+    *self.handle.write().unwrap_or_else(|error| error.into_inner()) = None;
+  }
+
+  /// Install the handle of a restored connection.
+  ///
+  /// Returns `false` if the supervisor is stopping, the connection should be
+  /// closed instead.
+  fn install(&self, handle: ChannelHandle<T>) -> bool {
+    let mut guard: RwLockWriteGuard<'_, Option<ChannelHandle<T>>> =
+      self.handle.write().unwrap_or_else(|error| error.into_inner());
+
+    if self.stopping.load(Ordering::SeqCst) {
+      return false;
+    }
+
+    *guard = Some(handle);
+
+    true
+  }
+
+  /// Mark the supervisor as stopping, returning the current handle.
+  ///
+  /// Returns `None` while a reconnect is in progress.
+  fn stop(&self) -> Option<ChannelHandle<T>> {
+    let guard: RwLockWriteGuard<'_, Option<ChannelHandle<T>>> =
+      self.handle.write().unwrap_or_else(|error| error.into_inner());
+
+    self.stopping.store(true, Ordering::SeqCst);
+
+    guard.clone()
+  }
+
+  #[inline]
+  fn is_stopping(&self) -> bool {
+    self.stopping.load(Ordering::SeqCst)
+  }
+}
+
+// =============================================================================
+// Supervisor
+// =============================================================================
+
+type NotifyRecv = ReceiverStream<Result<Notification, ChannelError>>;
+type NotifySend = mpsc::Sender<Result<Notification, ChannelError>>;
+
+/// A [`Channel`] that reconnects when the connection drops.
+///
+/// After every reconnect the session is restored by re-sending
+/// [`Authenticate`] with the stored API key and, if the bot was in chat,
+/// [`ChatConnect`].
+pub struct Supervisor<C: Connector> {
+  shared: Arc<Shared<C::Transport>>,
+  recv: NotifyRecv,
+  task: JoinHandle<()>,
+}
+
+impl<C: Connector> Supervisor<C> {
+  const BUFFER: usize = 0x20;
+
+  /// Open a supervised connection and authenticate with `api_key`.
+  pub async fn connect(
+    connector: C,
+    api_key: impl Into<String>,
+    backoff: Backoff,
+  ) -> Result<Self, ChannelError> {
+    let api_key: String = api_key.into();
+    let channel: Channel<C::Transport> = connector.connect().await?;
+
+    restore(channel.handle(), &api_key, false).await?;
+
+    let shared: Arc<Shared<C::Transport>> = Arc::new(Shared {
+      handle: RwLock::new(Some(channel.handle().clone())),
+      in_chat: AtomicBool::new(false),
+      stopping: AtomicBool::new(false),
+    });
+
+    let (send, recv) = mpsc::channel(Self::BUFFER);
+    let runtime: Runtime = channel.config().runtime.clone();
+
+    let supervise: Supervise<C> = Supervise {
+      connector,
+      api_key,
+      backoff,
+      shared: Arc::clone(&shared),
+      send,
+    };
+
+    Ok(Self {
+      shared,
+      recv: ReceiverStream::new(recv),
+      task: runtime.spawn(supervise.run(channel))?,
+    })
+  }
+
+  /// Waits for the supervisor task to finish.
+  ///
+  /// # Note
+  ///
+  /// [`stop`][Self::stop] or [`kill`][Self::kill] should be called prior,
+  /// otherwise this may block indefinitely.
+  #[inline]
+  pub async fn join(self) -> Result<(), ChannelError> {
+    self
+      .task
+      .await
+      .map_err(|error| ChannelError::new(ErrorKind::JoinError, error))
+  }
+
+  /// Gracefully stops the WebSocket server without reconnecting.
+  ///
+  /// A reconnect in progress is abandoned.
+  pub async fn stop(&self) -> Result<(), ChannelError> {
+    match self.shared.stop() {
+      Some(handle) => handle.stop().await,
+      None => Ok(()),
+    }
+  }
+
+  /// Forcibly stops the WebSocket server without reconnecting.
+  ///
+  /// A reconnect in progress is abandoned.
+  pub async fn kill(&self) -> Result<(), ChannelError> {
+    match self.shared.stop() {
+      Some(handle) => handle.kill().await,
+      None => Ok(()),
+    }
+  }
+
+  /// Returns a [`stream`][Stream] of events and lifecycle notifications.
+  pub fn event_stream(
+    &mut self,
+  ) -> impl Stream<Item = Result<Notification, ChannelError>> + '_ {
+    &mut self.recv
+  }
+}
+
+impl<C: Connector> Socket for Supervisor<C> {
+  type Error = ChannelError;
+
+  async fn send<P>(&self, payload: P) -> SocketResponse<Self>
+  where
+    P: Packet + IntoPayload,
+  {
+    let response = self.shared.handle()?.send(payload).await?;
+
+    // Remember whether the bot is in chat so the session can be restored
+    match P::REQ_TYPE {
+      RequestType::Connect => self.shared.in_chat.store(true, Ordering::SeqCst),
+      RequestType::Disconnect => self.shared.in_chat.store(false, Ordering::SeqCst),
+      _ => {}
+    }
+
+    Ok(response)
+  }
+}
+
+impl<C: Connector> Handler for Supervisor<C> {
+  type Error = ChannelError;
+
+  async fn call(&self, request: RequestPacket) -> Result<ResponsePacket, Self::Error> {
+    let command: RequestType = request.command();
+    let response: ResponsePacket = self.shared.handle()?.call(request).await?;
+
+    match command {
+      RequestType::Connect => self.shared.in_chat.store(true, Ordering::SeqCst),
+      RequestType::Disconnect => self.shared.in_chat.store(false, Ordering::SeqCst),
+      _ => {}
+    }
+
+    Ok(response)
+  }
+
+  #[inline]
+  fn status(error: &Self::Error) -> Option<ResponseStatus> {
+    error.response_status()
+  }
+}
+
+// =============================================================================
+// Internal Supervisor Task
+// =============================================================================
+
+struct Supervise<C: Connector> {
+  connector: C,
+  api_key: String,
+  backoff: Backoff,
+  shared: Arc<Shared<C::Transport>>,
+  send: NotifySend,
+}
+
+impl<C: Connector> Supervise<C> {
+  async fn run(self, mut channel: Channel<C::Transport>) {
+    loop {
+      // Forward events until the connection is lost
+      while let Some(event) = channel.event_stream().next().await {
+        if self.send.send(event.map(Notification::Event)).await.is_err() {
+          return;
+        }
+      }
+
+      self.shared.clear_handle();
+
+      // Errors were already reported through the event stream
+      let _ignore = channel.join().await;
+
+      if self.shared.is_stopping() {
+        return;
+      }
+
+      channel = match self.reconnect().await {
+        Some(channel) => channel,
+        None => return,
+      };
+
+      if !self.shared.install(channel.handle().clone()) {
+        close(channel).await;
+        return;
+      }
+
+      channel.config().metrics.reconnect();
+
+      if self.send.send(Ok(Notification::Restored)).await.is_err() {
+        return;
+      }
+    }
+  }
+
+  async fn reconnect(&self) -> Option<Channel<C::Transport>> {
+    let mut attempt: u32 = 0;
+
+    loop {
+      attempt += 1;
+
+      if self.backoff.exhausted(attempt) {
+        let _ignore = self.send.send(Ok(Notification::GaveUp)).await;
+        return None;
+      }
+
+      let delay: Duration = self.backoff.delay(attempt);
+      let notify: Notification = Notification::Reconnecting { attempt, delay };
+
+      self.send.send(Ok(notify)).await.ok()?;
+
+      runtime::sleep(delay).await;
+
+      if self.shared.is_stopping() {
+        return None;
+      }
+
+      let error: ChannelError = match self.connector.connect().await {
+        Ok(channel) if self.shared.is_stopping() => {
+          close(channel).await;
+          return None;
+        }
+        Ok(channel) => {
+          let in_chat: bool = self.shared.in_chat.load(Ordering::SeqCst);
+
+          match restore(channel.handle(), &self.api_key, in_chat).await {
+            Ok(()) if self.shared.is_stopping() => {
+              close(channel).await;
+              return None;
+            }
+            Ok(()) => return Some(channel),
+            Err(error) => error,
+          }
+        }
+        Err(error) => error,
+      };
+
+      self.send.send(Err(error)).await.ok()?;
+    }
+  }
+}
+
+/// Close a connection opened while the supervisor was stopping.
+async fn close<T: Transport>(channel: Channel<T>) {
+  // The connection is unused, a failed close handshake is of no concern.
+  let _ignore = channel.stop().await;
+  let _ignore = channel.join().await;
+}
+
+async fn restore<T: Transport>(
+  handle: &ChannelHandle<T>,
+  api_key: &str,
+  in_chat: bool,
+) -> Result<(), ChannelError> {
+  handle.send(Authenticate { api_key }).await?;
+
+  if in_chat {
+    handle.send(ChatConnect).await?;
+  }
+
+  Ok(())
+}
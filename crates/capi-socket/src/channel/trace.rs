@@ -0,0 +1,114 @@
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use std::fmt::Debug;
+use std::fmt::DebugStruct;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+
+use crate::channel::ChannelError;
+
+// =============================================================================
+// Redacted Request
+// =============================================================================
+
+/// Formats a request with credentials redacted.
+///
+/// Only `Authenticate` carries credentials (the API key), its whole payload
+/// is replaced with `<redacted>`.
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+pub(crate) struct Redacted<'a>(pub(crate) &'a RequestPacket);
+
+impl Debug for Redacted<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let mut debug: DebugStruct<'_, '_> = f.debug_struct("RequestPacket");
+
+    debug.field("command", &self.0.command());
+    debug.field("request_id", &self.0.request());
+
+    if self.0.command() == RequestType::Authenticate {
+      debug.field("payload", &format_args!("<redacted>"));
+    } else {
+      debug.field("payload", self.0.payload());
+    }
+
+    debug.finish()
+  }
+}
+
+// =============================================================================
+// Request Span
+// =============================================================================
+
+/// A span covering a request from the first attempt to the final response.
+///
+/// Does nothing unless the `tracing` feature is enabled.
+pub(crate) struct RequestSpan {
+  #[cfg(feature = "tracing")]
+  span: tracing::Span,
+  #[cfg(feature = "tracing")]
+  start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl RequestSpan {
+  pub(crate) fn new(request: &RequestPacket) -> Self {
+    use tracing::field::Empty;
+
+    Self {
+      span: tracing::debug_span!(
+        "request",
+        command = ?request.command(),
+        request_id = ?request.request(),
+        latency = Empty,
+        status = Empty,
+      ),
+      start: std::time::Instant::now(),
+    }
+  }
+
+  pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+    tracing::Instrument::instrument(future, self.span.clone())
+  }
+
+  pub(crate) fn finish(&self, output: &Result<ResponsePacket, ChannelError>) {
+    use tracing::field::debug;
+    use tracing::field::display;
+
+    self.span.record("latency", debug(self.start.elapsed()));
+
+    let _enter: tracing::span::Entered<'_> = self.span.enter();
+
+    match output {
+      Ok(_) => {
+        self.span.record("status", "ok");
+        tracing::debug!("request completed");
+      }
+      Err(error) => {
+        match error.response_status() {
+          Some(status) => self.span.record("status", display(status.as_str())),
+          None => self.span.record("status", display(error.kind())),
+        };
+
+        tracing::warn!(%error, "request failed");
+      }
+    }
+  }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl RequestSpan {
+  #[inline]
+  pub(crate) const fn new(_request: &RequestPacket) -> Self {
+    Self {}
+  }
+
+  #[inline]
+  pub(crate) fn instrument<F: Future>(&self, future: F) -> F {
+    future
+  }
+
+  #[inline]
+  pub(crate) fn finish(&self, _output: &Result<ResponsePacket, ChannelError>) {}
+}
@@ -1,4 +1,9 @@
+use serde::Deserialize;
+use serde::Serialize;
 use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
 use std::future::Future;
 
 use self::private::Connection;
@@ -26,21 +31,180 @@ mod private {
 
 pub trait Transport: Connection<Self::Message, Self::Invalid> + Unpin + Send + 'static {
   type Message: Message;
-  type Invalid: Error + Send;
+  /// The error of the connection.
+  ///
+  /// Must be `Sync`, transport errors are shared between event subscribers.
+  type Invalid: Error + Send + Sync;
 
+  /// Close the connection with a normal close code.
   // Note: async fn in trait does not currently apply `Send` bound.
   //
   // https://blog.rust-lang.org/inside-rust/2022/11/17/async-fn-in-trait-nightly.html
   fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_;
 }
 
+// =============================================================================
+// Local Transport
+// =============================================================================
+
+/// A [`Transport`] that is not required to be [`Send`].
+///
+/// Implemented for every `Transport`. A channel over a local transport must be
+/// driven by the caller, see
+/// [`Channel::with_driver`][crate::channel::Channel::with_driver].
+pub trait LocalTransport: Connection<Self::Message, Self::Invalid> + Unpin + 'static {
+  type Message: Message;
+  type Invalid: Error + Send + Sync;
+
+  /// Close the connection with a normal close code.
+  fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + '_;
+}
+
+impl<T: Transport> LocalTransport for T {
+  type Message = <T as Transport>::Message;
+  type Invalid = <T as Transport>::Invalid;
+
+  #[inline]
+  fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + '_ {
+    Transport::shutdown(self)
+  }
+}
+
 // =============================================================================
 // Transport Message
 // =============================================================================
 
-pub trait Message: Send + 'static {
-  type Error: Error + Send;
+pub trait Message: Sized + Send + 'static {
+  /// The error of reading a message.
+  ///
+  /// Must be `Sync`, transport errors are shared between event subscribers.
+  type Error: Error + Send + Sync;
 
   fn from_string(string: String) -> Self;
-  fn into_string(self) -> Result<String, Self::Error>;
+
+  /// Returns the data carried by the message.
+  ///
+  /// Control and close frames are errors.
+  ///
+  /// # Panics
+  ///
+  /// The default panics, transports implement either this method or
+  /// [`classify`][Self::classify].
+  #[deprecated(note = "use `Message::classify`, which keeps control and close frames")]
+  fn into_string(self) -> Result<String, Self::Error> {
+    unimplemented!("transports must implement `Message::classify`")
+  }
+
+  /// Create a reply to a ping frame carrying `payload`.
+  ///
+  /// Returns `None` if the transport answers pings on its own, which is the
+  /// default.
+  #[inline]
+  fn pong(payload: Vec<u8>) -> Option<Self> {
+    let _ = payload;
+    None
+  }
+
+  /// Create a ping frame carrying `payload`.
+  ///
+  /// Returns `None` if the transport cannot send pings, which disables the
+  /// [`heartbeat`][crate::channel::Heartbeat]. This is the default.
+  #[inline]
+  fn ping(payload: Vec<u8>) -> Option<Self> {
+    let _ = payload;
+    None
+  }
+
+  /// Classify the message as a data, control, or close frame.
+  ///
+  /// The default treats every message as data, read with
+  /// [`into_string`][Self::into_string], for transports written before this
+  /// method existed. New transports implement this method instead.
+  #[inline]
+  #[allow(deprecated)]
+  fn classify(self) -> Result<Frame, Self::Error> {
+    self.into_string().map(Frame::Data)
+  }
+}
+
+// =============================================================================
+// Transport Frame
+// =============================================================================
+
+/// A classified [`Message`].
+///
+/// Frames are also messages themselves, for transports that do not wrap a
+/// WebSocket library.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Frame {
+  /// A frame carrying chat API data.
+  Data(String),
+  /// A control frame, handled by the channel.
+  Control(Control),
+  /// The remote peer closed the connection.
+  Close(Option<CloseFrame>),
+}
+
+/// A WebSocket control frame.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Control {
+  Ping(Vec<u8>),
+  Pong(Vec<u8>),
+}
+
+/// The status code and reason sent with a close frame.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CloseFrame {
+  pub code: u16,
+  pub reason: String,
+}
+
+/// A control or close frame was read as data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotData(pub Frame);
+
+impl Display for NotData {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match self.0 {
+      Frame::Data(_) => f.write_str("data frame"),
+      Frame::Control(_) => f.write_str("expected data, found control frame"),
+      Frame::Close(_) => f.write_str("expected data, found close frame"),
+    }
+  }
+}
+
+impl Error for NotData {}
+
+impl Message for Frame {
+  type Error = NotData;
+
+  #[inline]
+  fn from_string(string: String) -> Self {
+    Self::Data(string)
+  }
+
+  #[inline]
+  fn pong(payload: Vec<u8>) -> Option<Self> {
+    Some(Self::Control(Control::Pong(payload)))
+  }
+
+  #[inline]
+  fn ping(payload: Vec<u8>) -> Option<Self> {
+    Some(Self::Control(Control::Ping(payload)))
+  }
+
+  #[inline]
+  fn into_string(self) -> Result<String, Self::Error> {
+    match self {
+      Self::Data(data) => Ok(data),
+      frame => Err(NotData(frame)),
+    }
+  }
+
+  #[inline]
+  fn classify(self) -> Result<Frame, Self::Error> {
+    Ok(self)
+  }
 }
@@ -1,4 +1,12 @@
 //! WebSocket extensions for Blizzard Classic Chat API (CAPI).
+//!
+//! # Tracing
+//!
+//! With the `tracing` feature, channels record a span for every request and
+//! log each request as it is queued, at `trace` level. The payload of
+//! `Authenticate` requests, which carries the API key, is logged as
+//! `<redacted>`. Other payloads, including chat messages and whispers, are
+//! logged as is.
 
 #![allow(async_fn_in_trait)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
@@ -9,11 +17,19 @@ mod macros;
 feature! {
   #[feature = "channel"]
   pub mod channel;
+  pub mod middleware;
 }
 
+feature! {
+  #[feature = "blocking"]
+  pub mod blocking;
+}
+
+pub mod session;
 pub mod socket;
 pub mod transport;
 
+pub use self::session::Session;
 pub use self::socket::Socket;
 pub use self::socket::SocketExt;
 pub use self::socket::SocketResponse;
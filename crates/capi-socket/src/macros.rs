@@ -10,3 +10,38 @@ macro_rules! feature {
     )*
   };
 }
+
+// Forward to the `tracing` macros if the feature is enabled, expand to nothing
+// otherwise. Only usable in statement position.
+
+#[cfg_attr(not(feature = "channel"), allow(unused_macros))]
+macro_rules! trace {
+  ($($tt:tt)*) => {
+    #[cfg(feature = "tracing")]
+    ::tracing::trace!($($tt)*);
+  };
+}
+
+#[cfg_attr(not(feature = "channel"), allow(unused_macros))]
+macro_rules! debug {
+  ($($tt:tt)*) => {
+    #[cfg(feature = "tracing")]
+    ::tracing::debug!($($tt)*);
+  };
+}
+
+#[cfg_attr(not(feature = "channel"), allow(unused_macros))]
+macro_rules! info {
+  ($($tt:tt)*) => {
+    #[cfg(feature = "tracing")]
+    ::tracing::info!($($tt)*);
+  };
+}
+
+#[cfg_attr(not(feature = "channel"), allow(unused_macros))]
+macro_rules! warn {
+  ($($tt:tt)*) => {
+    #[cfg(feature = "tracing")]
+    ::tracing::warn!($($tt)*);
+  };
+}
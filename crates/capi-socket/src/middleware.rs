@@ -0,0 +1,376 @@
+//! Composable request middleware.
+//!
+//! A [`Middleware`] sits between [`SocketExt`][crate::SocketExt] calls and a
+//! [`Handler`] that sends the request, and can inspect or modify the
+//! [`RequestPacket`], answer it without sending it, or post-process the
+//! [`ResponsePacket`]. Middleware is stacked over a handler with [`Stack`].
+
+use capi_core::IntoPayload;
+use capi_core::Packet;
+use capi_core::Payload;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use serde_json::Value;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::channel::limiter::Limiter;
+use crate::channel::runtime;
+use crate::channel::RateLimit;
+use crate::socket::Socket;
+use crate::socket::SocketResponse;
+
+// =============================================================================
+// Handler
+// =============================================================================
+
+/// Sends [`requests`][RequestPacket] and waits for the response.
+pub trait Handler {
+  type Error;
+
+  async fn call(&self, request: RequestPacket) -> Result<ResponsePacket, Self::Error>;
+
+  /// Returns the response status carried by `error`, if any.
+  ///
+  /// Lets middleware see statuses of responses the handler turned into
+  /// errors. Returns `None` by default.
+  #[inline]
+  fn status(error: &Self::Error) -> Option<ResponseStatus> {
+    let _ = error;
+    None
+  }
+}
+
+// =============================================================================
+// Middleware
+// =============================================================================
+
+/// A layer around a [`Handler`].
+///
+/// Calling `next` passes the request on to the rest of the stack, not calling
+/// it short-circuits the request.
+pub trait Middleware<S: Handler> {
+  async fn handle(&self, request: RequestPacket, next: &S) -> Result<ResponsePacket, S::Error>;
+}
+
+/// A [`Handler`] wrapped in a [`Middleware`].
+#[derive(Clone, Debug)]
+pub struct Layered<M, S> {
+  middleware: M,
+  inner: S,
+}
+
+impl<M, S> Handler for Layered<M, S>
+where
+  M: Middleware<S>,
+  S: Handler,
+{
+  type Error = S::Error;
+
+  #[inline]
+  async fn call(&self, request: RequestPacket) -> Result<ResponsePacket, Self::Error> {
+    self.middleware.handle(request, &self.inner).await
+  }
+
+  #[inline]
+  fn status(error: &Self::Error) -> Option<ResponseStatus> {
+    S::status(error)
+  }
+}
+
+// =============================================================================
+// Middleware Stack
+// =============================================================================
+
+/// A stack of [`Middleware`] over a [`Handler`].
+///
+/// The most recently added layer sees requests first and responses last.
+///
+/// The stack implements [`Socket`], so all [`SocketExt`][crate::SocketExt]
+/// methods pass through the middleware.
+#[derive(Clone, Debug)]
+pub struct Stack<S> {
+  inner: S,
+}
+
+impl<S: Handler> Stack<S> {
+  /// Create a new `Stack` without middleware over the given `handler`.
+  #[inline]
+  pub const fn new(handler: S) -> Self {
+    Self { inner: handler }
+  }
+
+  /// Add a layer on top of the stack.
+  #[inline]
+  pub fn layer<M: Middleware<S>>(self, middleware: M) -> Stack<Layered<M, S>> {
+    Stack {
+      inner: Layered {
+        middleware,
+        inner: self.inner,
+      },
+    }
+  }
+
+  /// Returns a reference to the wrapped handler.
+  #[inline]
+  pub const fn get_ref(&self) -> &S {
+    &self.inner
+  }
+
+  /// Consumes the stack, returning the wrapped handler.
+  #[inline]
+  pub fn into_inner(self) -> S {
+    self.inner
+  }
+}
+
+impl<S: Handler> Handler for Stack<S> {
+  type Error = S::Error;
+
+  #[inline]
+  async fn call(&self, request: RequestPacket) -> Result<ResponsePacket, Self::Error> {
+    self.inner.call(request).await
+  }
+
+  #[inline]
+  fn status(error: &Self::Error) -> Option<ResponseStatus> {
+    S::status(error)
+  }
+}
+
+impl<S: Handler> Socket for Stack<S> {
+  type Error = S::Error;
+
+  #[inline]
+  async fn send<P>(&self, payload: P) -> SocketResponse<Self>
+  where
+    P: Packet + IntoPayload,
+  {
+    self.inner.call(RequestPacket::new(payload)).await
+  }
+}
+
+// =============================================================================
+// Dry Run
+// =============================================================================
+
+/// Answers chat and moderation requests without sending them.
+///
+/// Authentication and connection requests are sent as usual, so a bot can go
+/// through its normal startup without affecting the channel.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DryRun;
+
+impl<S: Handler> Middleware<S> for DryRun {
+  async fn handle(&self, request: RequestPacket, next: &S) -> Result<ResponsePacket, S::Error> {
+    match request.command() {
+      RequestType::Authenticate | RequestType::Connect | RequestType::Disconnect => {
+        next.call(request).await
+      }
+      command => Ok(ResponsePacket::new(
+        command.response(),
+        request.request(),
+        Payload::new(),
+        None,
+      )),
+    }
+  }
+}
+
+// =============================================================================
+// Sanitize
+// =============================================================================
+
+/// Cleans up the `message` of outgoing chat messages, whispers and emotes.
+///
+/// Control characters (including line breaks) are removed, and the message is
+/// optionally truncated.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sanitize {
+  max_length: Option<usize>,
+}
+
+impl Sanitize {
+  #[inline]
+  pub const fn new() -> Self {
+    Self { max_length: None }
+  }
+
+  /// Set the maximum number of characters in a message.
+  #[inline]
+  pub const fn max_length(mut self, max_length: Option<usize>) -> Self {
+    self.max_length = max_length;
+    self
+  }
+
+  fn clean(&self, message: &str) -> String {
+    let chars = message.chars().filter(|char| !char.is_control());
+
+    match self.max_length {
+      Some(max_length) => chars.take(max_length).collect(),
+      None => chars.collect(),
+    }
+  }
+}
+
+impl<S: Handler> Middleware<S> for Sanitize {
+  async fn handle(&self, mut request: RequestPacket, next: &S) -> Result<ResponsePacket, S::Error> {
+    if let Some(Value::String(message)) = request.payload_mut().get_mut("message") {
+      *message = self.clean(message);
+    }
+
+    next.call(request).await
+  }
+}
+
+// =============================================================================
+// Retry
+// =============================================================================
+
+/// Re-sends requests that failed with an error accepted by a predicate.
+///
+/// Each attempt is sent with a new request ID, so a late response to an
+/// earlier attempt is never mistaken for the response to a retry.
+#[derive(Clone, Copy)]
+pub struct Retry<F> {
+  attempts: u32,
+  delay: Duration,
+  predicate: F,
+}
+
+impl<F> Retry<F> {
+  /// Retry up to `attempts` times when `predicate` returns `true` for the error.
+  #[inline]
+  pub const fn new(attempts: u32, predicate: F) -> Self {
+    Self {
+      attempts,
+      delay: Duration::ZERO,
+      predicate,
+    }
+  }
+
+  /// Set the time to wait before each retry.
+  #[inline]
+  pub const fn delay(mut self, delay: Duration) -> Self {
+    self.delay = delay;
+    self
+  }
+}
+
+impl<F> Debug for Retry<F> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Retry")
+      .field("attempts", &self.attempts)
+      .field("delay", &self.delay)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<S, F> Middleware<S> for Retry<F>
+where
+  S: Handler,
+  F: Fn(&S::Error) -> bool,
+{
+  async fn handle(&self, request: RequestPacket, next: &S) -> Result<ResponsePacket, S::Error> {
+    let mut attempt: u32 = 0;
+    let mut request: RequestPacket = request;
+
+    loop {
+      match next.call(request.clone()).await {
+        Err(error) if attempt < self.attempts && (self.predicate)(&error) => {
+          attempt += 1;
+          request = request.renew();
+
+          if !self.delay.is_zero() {
+            runtime::sleep(self.delay).await;
+          }
+        }
+        output => return output,
+      }
+    }
+  }
+}
+
+// =============================================================================
+// Inspect
+// =============================================================================
+
+/// Calls a function with every request and its outcome.
+#[derive(Clone, Copy)]
+pub struct Inspect<F> {
+  inspect: F,
+}
+
+impl<F> Inspect<F> {
+  #[inline]
+  pub const fn new(inspect: F) -> Self {
+    Self { inspect }
+  }
+}
+
+impl<F> Debug for Inspect<F> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Inspect").finish_non_exhaustive()
+  }
+}
+
+impl<S, F> Middleware<S> for Inspect<F>
+where
+  S: Handler,
+  F: Fn(&RequestPacket, &Result<ResponsePacket, S::Error>),
+{
+  async fn handle(&self, request: RequestPacket, next: &S) -> Result<ResponsePacket, S::Error> {
+    let output: Result<ResponsePacket, S::Error> = next.call(request.clone()).await;
+
+    (self.inspect)(&request, &output);
+
+    output
+  }
+}
+
+// =============================================================================
+// Throttle
+// =============================================================================
+
+/// Waits for a token-bucket budget before passing requests on.
+///
+/// Unlike [`ChannelConfig::rate_limit`][crate::channel::ChannelConfig::rate_limit],
+/// the budget can be placed in front of other middleware. Clones share the
+/// budget, so a single throttle can limit several channels.
+#[derive(Clone, Debug)]
+pub struct Throttle {
+  limiter: Arc<Limiter>,
+}
+
+impl Throttle {
+  #[inline]
+  pub fn new(rate_limit: RateLimit) -> Self {
+    Self {
+      limiter: Arc::new(Limiter::from(rate_limit)),
+    }
+  }
+}
+
+impl<S: Handler> Middleware<S> for Throttle {
+  async fn handle(&self, request: RequestPacket, next: &S) -> Result<ResponsePacket, S::Error> {
+    let command: RequestType = request.command();
+
+    self.limiter.acquire(command).await;
+
+    let output: Result<ResponsePacket, S::Error> = next.call(request).await;
+    let status: Option<ResponseStatus> = match output {
+      Ok(ref response) => response.status(),
+      Err(ref error) => S::status(error),
+    };
+    let limited: bool = status.is_some_and(|status| status.is_rate_limited());
+
+    self.limiter.update(command, limited);
+
+    output
+  }
+}
@@ -0,0 +1,299 @@
+use capi_core::packet::Authenticate;
+use capi_core::packet::ChatBanUser;
+use capi_core::packet::ChatConnect;
+use capi_core::packet::ChatDisconnect;
+use capi_core::packet::ChatKickUser;
+use capi_core::packet::ChatSendEmote;
+use capi_core::packet::ChatSendMessage;
+use capi_core::packet::ChatSendWhisper;
+use capi_core::packet::ChatSetModerator;
+use capi_core::packet::ChatUnbanUser;
+use capi_core::types::UserID;
+use capi_core::IntoPayload;
+use capi_core::Packet;
+use capi_core::ResponseStatus;
+use std::any::type_name;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::marker::PhantomData;
+
+use crate::socket::Socket;
+use crate::socket::SocketResponse;
+
+// =============================================================================
+// Session State
+// =============================================================================
+
+mod private {
+  pub trait Sealed {}
+}
+
+/// The state of a [`Session`].
+pub trait State: private::Sealed {}
+
+/// The session has not been authenticated.
+#[derive(Debug)]
+pub enum Unauthenticated {}
+
+/// The session has been authenticated but the bot is not in chat.
+#[derive(Debug)]
+pub enum Authenticated {}
+
+/// The bot is connected to the chat channel.
+#[derive(Debug)]
+pub enum InChat {}
+
+impl private::Sealed for Unauthenticated {}
+impl private::Sealed for Authenticated {}
+impl private::Sealed for InChat {}
+
+impl State for Unauthenticated {}
+impl State for Authenticated {}
+impl State for InChat {}
+
+// =============================================================================
+// Transition Error
+// =============================================================================
+
+#[derive(Debug)]
+enum Cause<E> {
+  Socket(E),
+  Status(ResponseStatus),
+}
+
+/// A failed [`Session`] state transition.
+///
+/// The request either failed in the socket, or was answered with an error
+/// status. The session is handed back in the state it was in before the
+/// transition.
+pub struct TransitionError<S: Socket, T: State> {
+  session: Session<S, T>,
+  cause: Cause<S::Error>,
+}
+
+impl<S: Socket, T: State> TransitionError<S, T> {
+  /// Returns a reference to the socket error, if the request failed in the
+  /// socket.
+  #[inline]
+  pub const fn error(&self) -> Option<&S::Error> {
+    match self.cause {
+      Cause::Socket(ref error) => Some(error),
+      Cause::Status(_) => None,
+    }
+  }
+
+  /// Returns the response status, if the request was answered with an error.
+  #[inline]
+  pub const fn status(&self) -> Option<ResponseStatus> {
+    match self.cause {
+      Cause::Socket(_) => None,
+      Cause::Status(status) => Some(status),
+    }
+  }
+
+  /// Consumes the error, returning the session.
+  #[inline]
+  pub fn into_session(self) -> Session<S, T> {
+    self.session
+  }
+
+  /// Consumes the error, returning the session and socket error, if any.
+  #[inline]
+  pub fn into_parts(self) -> (Session<S, T>, Option<S::Error>) {
+    match self.cause {
+      Cause::Socket(error) => (self.session, Some(error)),
+      Cause::Status(_) => (self.session, None),
+    }
+  }
+}
+
+impl<S: Socket, T: State> Debug for TransitionError<S, T>
+where
+  S::Error: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("TransitionError")
+      .field("cause", &self.cause)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<S: Socket, T: State> Display for TransitionError<S, T>
+where
+  S::Error: Display,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match self.cause {
+      Cause::Socket(ref error) => Display::fmt(error, f),
+      Cause::Status(status) => write!(f, "request failed: {}", status.as_str()),
+    }
+  }
+}
+
+impl<S: Socket, T: State> Error for TransitionError<S, T>
+where
+  S::Error: Error + 'static,
+{
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self.cause {
+      Cause::Socket(ref error) => Some(error),
+      Cause::Status(_) => None,
+    }
+  }
+}
+
+// =============================================================================
+// Session
+// =============================================================================
+
+/// A [`Socket`] wrapper that only exposes the requests valid in its [`State`].
+///
+/// ```text
+/// Unauthenticated --authenticate--> Authenticated --connect--> InChat
+///                                                 <-disconnect-
+/// ```
+///
+/// A transition only happens if the request succeeds, otherwise the session is
+/// returned unchanged in a [`TransitionError`]. The socket is not exposed, as
+/// it would allow any request in any state; [`into_inner`][Self::into_inner]
+/// gives it up along with the session.
+///
+/// Chat requests cannot be sent before connecting:
+///
+/// ```compile_fail
+/// # use capi_socket::channel::Channel;
+/// # use capi_socket::transport::memory::Memory;
+/// # use capi_socket::Session;
+/// # async fn example(channel: Channel<Memory>) {
+/// let session: Session<Channel<Memory>> = Session::new(channel);
+///
+/// session.send_message("hello").await;
+/// # }
+/// ```
+pub struct Session<S, T: State = Unauthenticated> {
+  socket: S,
+  state: PhantomData<fn() -> T>,
+}
+
+impl<S: Socket, T: State> Session<S, T> {
+  #[inline]
+  const fn from_socket(socket: S) -> Self {
+    Self {
+      socket,
+      state: PhantomData,
+    }
+  }
+
+  /// Consumes the session, returning the underlying socket.
+  #[inline]
+  pub fn into_inner(self) -> S {
+    self.socket
+  }
+
+  async fn transition<P, U>(self, payload: P) -> Result<Session<S, U>, TransitionError<S, T>>
+  where
+    P: Packet + IntoPayload,
+    U: State,
+  {
+    let cause: Cause<S::Error> = match self.socket.send(payload).await {
+      Ok(response) => match response.status() {
+        Some(status) if !status.is_ok() => Cause::Status(status),
+        _ => return Ok(Session::from_socket(self.socket)),
+      },
+      Err(error) => Cause::Socket(error),
+    };
+
+    Err(TransitionError {
+      session: self,
+      cause,
+    })
+  }
+}
+
+impl<S: Debug, T: State> Debug for Session<S, T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Session")
+      .field("socket", &self.socket)
+      .field("state", &type_name::<T>())
+      .finish()
+  }
+}
+
+impl<S: Socket> Session<S, Unauthenticated> {
+  /// Create a new unauthenticated `Session` over the given `socket`.
+  #[inline]
+  pub const fn new(socket: S) -> Self {
+    Self::from_socket(socket)
+  }
+
+  /// Authenticate with the API key.
+  pub async fn authenticate(
+    self,
+    api_key: &str,
+  ) -> Result<Session<S, Authenticated>, TransitionError<S, Unauthenticated>> {
+    self.transition(Authenticate { api_key }).await
+  }
+}
+
+impl<S: Socket> Session<S, Authenticated> {
+  /// Connect the bot to the gateway and chat channel.
+  ///
+  /// @event: { channel: String }
+  pub async fn connect(self) -> Result<Session<S, InChat>, TransitionError<S, Authenticated>> {
+    self.transition(ChatConnect).await
+  }
+}
+
+impl<S: Socket> Session<S, InChat> {
+  /// Disconnects the bot from the gateway and chat channel.
+  pub async fn disconnect(self) -> Result<Session<S, Authenticated>, TransitionError<S, InChat>> {
+    self.transition(ChatDisconnect).await
+  }
+
+  /// Sends a chat message to the channel.
+  #[inline]
+  pub async fn send_message(&self, message: &str) -> SocketResponse<S> {
+    self.socket.send(ChatSendMessage { message }).await
+  }
+
+  /// Sends a chat message to one user in the channel.
+  #[inline]
+  pub async fn send_whisper(&self, message: &str, user_id: UserID) -> SocketResponse<S> {
+    self.socket.send(ChatSendWhisper { message, user_id }).await
+  }
+
+  /// Sends an emote on behalf of a bot.
+  #[inline]
+  pub async fn send_emote(&self, message: &str) -> SocketResponse<S> {
+    self.socket.send(ChatSendEmote { message }).await
+  }
+
+  /// Bans a user from the channel.
+  #[inline]
+  pub async fn ban_user(&self, user_id: UserID) -> SocketResponse<S> {
+    self.socket.send(ChatBanUser { user_id }).await
+  }
+
+  /// Un-Bans a user from the channel.
+  #[inline]
+  pub async fn unban_user(&self, toon_name: &str) -> SocketResponse<S> {
+    self.socket.send(ChatUnbanUser { toon_name }).await
+  }
+
+  /// Kicks a user from the channel.
+  #[inline]
+  pub async fn kick_user(&self, user_id: UserID) -> SocketResponse<S> {
+    self.socket.send(ChatKickUser { user_id }).await
+  }
+
+  /// Sets the current chat moderator to a member of the current chat.
+  ///
+  /// Note: Same as a normal user doing `/desginate` followed by `/resign`.
+  #[inline]
+  pub async fn set_moderator(&self, user_id: UserID) -> SocketResponse<S> {
+    self.socket.send(ChatSetModerator { user_id }).await
+  }
+}
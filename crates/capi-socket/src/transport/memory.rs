@@ -0,0 +1,136 @@
+//! An in-process transport.
+//!
+//! [`pair`] creates two connected [`Memory`] transports exchanging
+//! [`Frame`]s, one for a [`Channel`][crate::channel::Channel] and one for a
+//! server such as [`MockServer`][crate::transport::mock::MockServer].
+
+use futures_util::Sink;
+use futures_util::Stream;
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::ready;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use tokio::sync::mpsc;
+
+use crate::channel::CloseFrame;
+use crate::channel::Frame;
+use crate::channel::Transport;
+
+/// The close code of a normal closure.
+const NORMAL: u16 = 1000;
+
+// =============================================================================
+// Memory Error
+// =============================================================================
+
+/// The other end of a [`Memory`] transport is gone.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Disconnected;
+
+impl Display for Disconnected {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.write_str("peer disconnected")
+  }
+}
+
+impl Error for Disconnected {}
+
+// =============================================================================
+// Memory Transport
+// =============================================================================
+
+/// Creates a pair of connected [`Memory`] transports.
+pub fn pair() -> (Memory, Memory) {
+  let (send_a, recv_a): (mpsc::UnboundedSender<Frame>, _) = mpsc::unbounded_channel();
+  let (send_b, recv_b): (mpsc::UnboundedSender<Frame>, _) = mpsc::unbounded_channel();
+
+  (
+    Memory {
+      send: send_a,
+      recv: recv_b,
+    },
+    Memory {
+      send: send_b,
+      recv: recv_a,
+    },
+  )
+}
+
+/// One end of an in-process connection.
+///
+/// The stream ends when the other end is dropped.
+#[derive(Debug)]
+pub struct Memory {
+  send: mpsc::UnboundedSender<Frame>,
+  recv: mpsc::UnboundedReceiver<Frame>,
+}
+
+impl Memory {
+  /// Send `frame` to the other end without going through the sink.
+  #[inline]
+  pub fn push(&self, frame: Frame) -> Result<(), Disconnected> {
+    self.send.send(frame).map_err(|_| Disconnected)
+  }
+
+  /// Returns a sender that writes to the other end.
+  #[inline]
+  #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+  pub(crate) fn sender(&self) -> mpsc::UnboundedSender<Frame> {
+    self.send.clone()
+  }
+}
+
+impl Stream for Memory {
+  type Item = Result<Frame, Disconnected>;
+
+  #[inline]
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.recv.poll_recv(cx).map(|frame| frame.map(Ok))
+  }
+}
+
+impl Sink<Frame> for Memory {
+  type Error = Disconnected;
+
+  #[inline]
+  fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  #[inline]
+  fn start_send(self: Pin<&mut Self>, item: Frame) -> Result<(), Self::Error> {
+    self.push(item)
+  }
+
+  #[inline]
+  fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  #[inline]
+  fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+}
+
+impl Transport for Memory {
+  type Message = Frame;
+  type Invalid = Disconnected;
+
+  fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+    let frame: Frame = Frame::Close(Some(CloseFrame {
+      code: NORMAL,
+      reason: String::new(),
+    }));
+
+    // The other end may already be gone, which is as good as closed.
+    let _ignore = self.push(frame);
+
+    ready(Ok(()))
+  }
+}
@@ -0,0 +1,576 @@
+//! A scripted fake chat API server.
+//!
+//! [`MockServer`] serves [`Memory`] transports and follows the chat API
+//! session state machine, so bots can be tested without the live endpoint or
+//! an API key. Tests drive the channel through the server handle: users join,
+//! leave, and talk, arbitrary events and response statuses can be injected,
+//! and responses can be held back.
+
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::Payload;
+use capi_core::RequestID;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use capi_core::Stage;
+use capi_core::types::UserID;
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::from_str;
+use serde_json::to_string;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task;
+
+use crate::channel::Channel;
+use crate::channel::ChannelConfig;
+use crate::channel::Control;
+use crate::channel::Frame;
+use crate::channel::limiter::Limiter;
+use crate::channel::RateLimit;
+use crate::transport::memory;
+use crate::transport::memory::Memory;
+
+// =============================================================================
+// Session State
+// =============================================================================
+
+struct Session {
+  send: UnboundedSender<Frame>,
+  stage: Stage,
+  limiter: Option<Limiter>,
+}
+
+impl Session {
+  #[inline]
+  fn send<T: Serialize>(&self, packet: &T) {
+    send(&self.send, packet);
+  }
+}
+
+fn send<T: Serialize>(send: &UnboundedSender<Frame>, packet: &T) {
+  if let Ok(data) = to_string(packet) {
+    // The connection may be closing, the session is removed when it ends.
+    let _ignore = send.send(Frame::Data(data));
+  }
+}
+
+#[derive(Clone, Debug)]
+struct User {
+  toon_name: String,
+  flags: Vec<String>,
+}
+
+/// A scripted answer to a request.
+#[derive(Clone, Copy, Debug)]
+enum Script {
+  Status(ResponseStatus),
+  Hold,
+}
+
+// =============================================================================
+// Server State
+// =============================================================================
+
+struct State {
+  users: BTreeMap<UserID, User>,
+  banned: BTreeSet<String>,
+  statuses: VecDeque<(RequestType, Script)>,
+  held: Vec<(u64, RequestPacket)>,
+  requests: Vec<RequestPacket>,
+  sessions: BTreeMap<u64, Session>,
+  next_session: u64,
+  next_event: u64,
+  rate_limit: Option<RateLimit>,
+}
+
+impl State {
+  fn event_id(&mut self) -> RequestID {
+    self.next_event += 1;
+    RequestID::new(self.next_event)
+  }
+
+  /// Send `event` to every session in chat.
+  fn broadcast(&self, event: &EventPacket) {
+    for session in self.sessions.values() {
+      if session.stage == Stage::InChat {
+        session.send(event);
+      }
+    }
+  }
+
+  fn user_update(&mut self, user_id: UserID, request: Option<RequestID>) -> Option<EventPacket> {
+    let user: &User = self.users.get(&user_id)?;
+
+    let mut payload: Payload = Payload::new();
+    payload.insert("user_id", user_id);
+    payload.insert("toon_name", user.toon_name.as_str());
+    payload.insert("flags", user.flags.clone());
+
+    let request: RequestID = request.unwrap_or_else(|| self.event_id());
+
+    Some(EventPacket::new(EventType::UserUpdate, request, payload))
+  }
+
+  fn user_leave(&mut self, user_id: UserID, request: Option<RequestID>) -> EventPacket {
+    let request: RequestID = request.unwrap_or_else(|| self.event_id());
+    EventPacket::new(EventType::UserLeave, request, Payload::from_kv("user_id", user_id))
+  }
+
+  /// Returns the status of `request` sent by a session in `stage`.
+  fn check(&self, stage: Stage, api_key: &str, request: &RequestPacket) -> ResponseStatus {
+    if let Err(status) = stage.check(request.command()) {
+      return status;
+    }
+
+    let payload: &Payload = request.payload();
+
+    match request.command() {
+      RequestType::Authenticate => {
+        if payload.get("api_key").and_then(Value::as_str) == Some(api_key) {
+          ResponseStatus::OK
+        } else {
+          ResponseStatus::BAD_REQUEST
+        }
+      }
+      RequestType::Connect | RequestType::Disconnect => ResponseStatus::OK,
+      RequestType::SendMessage | RequestType::SendEmote => {
+        if payload.get("message").is_some_and(Value::is_string) {
+          ResponseStatus::OK
+        } else {
+          ResponseStatus::BAD_REQUEST
+        }
+      }
+      RequestType::UnbanUser => match payload.get("toon_name").and_then(Value::as_str) {
+        Some(toon_name) if self.banned.contains(toon_name) => ResponseStatus::OK,
+        _ => ResponseStatus::BAD_REQUEST,
+      },
+      RequestType::SendWhisper
+      | RequestType::BanUser
+      | RequestType::KickUser
+      | RequestType::SetModerator => match user_id(payload) {
+        Some(user_id) if self.users.contains_key(&user_id) => ResponseStatus::OK,
+        _ => ResponseStatus::BAD_REQUEST,
+      },
+    }
+  }
+
+  fn handle(&mut self, session: u64, api_key: &str, channel: &str, request: RequestPacket) {
+    let command: RequestType = request.command();
+
+    let Some((stage, allowed)) = self
+      .sessions
+      .get(&session)
+      .map(|session| {
+        let allowed: bool = session
+          .limiter
+          .as_ref()
+          .is_none_or(|limiter| limiter.try_acquire(command));
+
+        (session.stage, allowed)
+      })
+    else {
+      return;
+    };
+
+    self.requests.push(request.clone());
+
+    let injected: Option<usize> = self.statuses.iter().position(|(kind, _)| *kind == command);
+
+    let status: ResponseStatus = match injected.and_then(|index| self.statuses.remove(index)) {
+      Some((_, Script::Status(status))) => status,
+      Some((_, Script::Hold)) => {
+        self.held.push((session, request));
+        return;
+      }
+      None if !allowed => ResponseStatus::RATE_LIMITED,
+      None => self.check(stage, api_key, &request),
+    };
+
+    self.answer(session, channel, request, status);
+  }
+
+  /// Answer the requests held back so far, in order.
+  fn release(&mut self, api_key: &str, channel: &str) {
+    for (session, request) in mem::take(&mut self.held) {
+      // The session may have ended in the meantime.
+      let Some(stage) = self.sessions.get(&session).map(|session| session.stage) else {
+        continue;
+      };
+
+      let status: ResponseStatus = self.check(stage, api_key, &request);
+
+      self.answer(session, channel, request, status);
+    }
+  }
+
+  /// Send the response to `request` and apply its effects.
+  fn answer(&mut self, session: u64, channel: &str, request: RequestPacket, status: ResponseStatus) {
+    let command: RequestType = request.command();
+
+    let response: ResponsePacket = ResponsePacket::new(
+      command.response(),
+      request.request(),
+      Payload::new(),
+      Some(status).filter(|status| !status.is_ok()),
+    );
+
+    let Some(target) = self.sessions.get_mut(&session) else {
+      // The connection ended while the request was held.
+      return;
+    };
+
+    target.send(&response);
+
+    if !status.is_ok() {
+      return;
+    }
+
+    target.stage = target.stage.next(command);
+
+    let target: UnboundedSender<Frame> = target.send.clone();
+
+    let request_id: RequestID = request.request();
+
+    match command {
+      RequestType::Connect => {
+        let payload: Payload = Payload::from_kv("channel", channel);
+        let event: EventPacket = EventPacket::new(EventType::Connect, request_id, payload);
+
+        send(&target, &event);
+
+        let users: Vec<UserID> = self.users.keys().copied().collect();
+
+        for user_id in users {
+          if let Some(event) = self.user_update(user_id, Some(request_id)) {
+            send(&target, &event);
+          }
+        }
+      }
+      RequestType::Disconnect => {
+        let event: EventPacket = EventPacket::new(EventType::Disconnect, request_id, Payload::new());
+
+        send(&target, &event);
+      }
+      RequestType::BanUser | RequestType::KickUser => {
+        let Some(user_id) = user_id(request.payload()) else {
+          return;
+        };
+
+        if let Some(user) = self.users.remove(&user_id) {
+          if command == RequestType::BanUser {
+            self.banned.insert(user.toon_name);
+          }
+
+          let event: EventPacket = self.user_leave(user_id, Some(request_id));
+          self.broadcast(&event);
+        }
+      }
+      RequestType::UnbanUser => {
+        if let Some(toon_name) = request.payload().get("toon_name").and_then(Value::as_str) {
+          self.banned.remove(toon_name);
+        }
+      }
+      RequestType::SetModerator => {
+        let Some(user_id) = user_id(request.payload()) else {
+          return;
+        };
+
+        if let Some(user) = self.users.get_mut(&user_id) {
+          if !user.flags.iter().any(|flag| flag == MODERATOR) {
+            user.flags.push(MODERATOR.to_owned());
+          }
+        }
+
+        if let Some(event) = self.user_update(user_id, Some(request_id)) {
+          self.broadcast(&event);
+        }
+      }
+      RequestType::Authenticate
+      | RequestType::SendMessage
+      | RequestType::SendWhisper
+      | RequestType::SendEmote => {}
+    }
+  }
+}
+
+// =============================================================================
+// Mock Server
+// =============================================================================
+
+struct Shared {
+  api_key: String,
+  channel: String,
+  state: Mutex<State>,
+}
+
+impl Shared {
+  fn lock(&self) -> MutexGuard<'_, State> {
+    self.state.lock().unwrap_or_else(|error| error.into_inner())
+  }
+}
+
+/// An in-process fake of the chat API server.
+///
+/// Each [`transport`][Self::transport] is served by its own session, which
+/// must authenticate with the configured API key and connect before any chat
+/// request is accepted; chat requests before that are answered with
+/// [`NOT_CONNECTED`][ResponseStatus::NOT_CONNECTED]. Users are shared by all
+/// sessions, events about them are sent to every session in chat.
+///
+/// Sessions are served by tokio tasks, the server must be used from within a
+/// tokio runtime.
+#[derive(Clone)]
+pub struct MockServer {
+  shared: Arc<Shared>,
+}
+
+impl MockServer {
+  /// Create a new `MockServer` accepting `api_key` and hosting `channel`.
+  pub fn new(api_key: impl Into<String>, channel: impl Into<String>) -> Self {
+    Self {
+      shared: Arc::new(Shared {
+        api_key: api_key.into(),
+        channel: channel.into(),
+        state: Mutex::new(State {
+          users: BTreeMap::new(),
+          banned: BTreeSet::new(),
+          statuses: VecDeque::new(),
+          held: Vec::new(),
+          requests: Vec::new(),
+          sessions: BTreeMap::new(),
+          next_session: 0,
+          next_event: 0,
+          rate_limit: None,
+        }),
+      }),
+    }
+  }
+
+  /// Open a new session, returning the client end of its transport.
+  pub fn transport(&self) -> Memory {
+    let (client, server): (Memory, Memory) = memory::pair();
+
+    let session: u64 = {
+      let mut state: MutexGuard<'_, State> = self.shared.lock();
+
+      state.next_session += 1;
+
+      let session: u64 = state.next_session;
+      let limiter: Option<Limiter> = state.rate_limit.map(Limiter::from);
+
+      state.sessions.insert(session, Session {
+        send: server.sender(),
+        stage: Stage::Unauthenticated,
+        limiter,
+      });
+
+      session
+    };
+
+    task::spawn(serve(Arc::clone(&self.shared), session, server));
+
+    client
+  }
+
+  /// Open a new session and create a [`Channel`] over it.
+  #[inline]
+  pub fn connect(&self) -> Channel<Memory> {
+    Channel::new(self.transport())
+  }
+
+  /// Open a new session and create a [`Channel`] over it with the given `config`.
+  #[inline]
+  pub fn connect_with_config(&self, config: ChannelConfig) -> Channel<Memory> {
+    Channel::with_config(self.transport(), config)
+  }
+
+  /// Set the request budget of each session, `None` disables rate limiting.
+  ///
+  /// Applies to sessions opened afterwards. Requests over budget are answered
+  /// with [`RATE_LIMITED`][ResponseStatus::RATE_LIMITED].
+  pub fn set_rate_limit(&self, rate_limit: Option<RateLimit>) {
+    self.shared.lock().rate_limit = rate_limit;
+  }
+
+  /// Add a user to the channel.
+  ///
+  /// Returns `false` if the user is banned.
+  pub fn join(&self, user_id: UserID, toon_name: &str) -> bool {
+    let mut state: MutexGuard<'_, State> = self.shared.lock();
+
+    if state.banned.contains(toon_name) {
+      return false;
+    }
+
+    state.users.insert(user_id, User {
+      toon_name: toon_name.to_owned(),
+      flags: Vec::new(),
+    });
+
+    if let Some(event) = state.user_update(user_id, None) {
+      state.broadcast(&event);
+    }
+
+    true
+  }
+
+  /// Set the flags (e.g. `"Moderator"`) of a user in the channel.
+  pub fn set_flags(&self, user_id: UserID, flags: &[&str]) {
+    let mut state: MutexGuard<'_, State> = self.shared.lock();
+
+    let Some(user) = state.users.get_mut(&user_id) else {
+      return;
+    };
+
+    user.flags = flags.iter().map(|flag| (*flag).to_owned()).collect();
+
+    if let Some(event) = state.user_update(user_id, None) {
+      state.broadcast(&event);
+    }
+  }
+
+  /// Remove a user from the channel.
+  pub fn leave(&self, user_id: UserID) {
+    let mut state: MutexGuard<'_, State> = self.shared.lock();
+
+    if state.users.remove(&user_id).is_some() {
+      let event: EventPacket = state.user_leave(user_id, None);
+      state.broadcast(&event);
+    }
+  }
+
+  /// A user sends a message to the channel.
+  #[inline]
+  pub fn say(&self, user_id: UserID, message: &str) {
+    self.message(user_id, message, "Channel");
+  }
+
+  /// A user whispers to the bot.
+  #[inline]
+  pub fn whisper(&self, user_id: UserID, message: &str) {
+    self.message(user_id, message, "Whisper");
+  }
+
+  /// A user sends an emote to the channel.
+  #[inline]
+  pub fn emote(&self, user_id: UserID, message: &str) {
+    self.message(user_id, message, "Emote");
+  }
+
+  /// Send `event` to every session in chat.
+  pub fn inject(&self, event: &EventPacket) {
+    self.shared.lock().broadcast(event);
+  }
+
+  /// Answer the next request of type `command` with `status`.
+  ///
+  /// Injected statuses replace the status the server would have sent, and are
+  /// used in the order they were injected, along with [`hold`][Self::hold].
+  /// Requests answered with an error have no effect.
+  pub fn respond_with(&self, command: RequestType, status: ResponseStatus) {
+    let script: Script = Script::Status(status);
+    self.shared.lock().statuses.push_back((command, script));
+  }
+
+  /// Leave the next request of type `command` unanswered until
+  /// [`release`][Self::release] is called.
+  pub fn hold(&self, command: RequestType) {
+    self.shared.lock().statuses.push_back((command, Script::Hold));
+  }
+
+  /// Answer all requests held back by [`hold`][Self::hold], in order.
+  ///
+  /// Requests of sessions that have ended since are discarded.
+  pub fn release(&self) {
+    let shared: &Shared = &self.shared;
+    shared.lock().release(&shared.api_key, &shared.channel);
+  }
+
+  /// Returns all requests received so far, in order.
+  pub fn requests(&self) -> Vec<RequestPacket> {
+    self.shared.lock().requests.clone()
+  }
+
+  /// Returns the users in the channel.
+  pub fn users(&self) -> Vec<UserID> {
+    self.shared.lock().users.keys().copied().collect()
+  }
+
+  /// Returns `true` if a user with the given `toon_name` is banned.
+  pub fn is_banned(&self, toon_name: &str) -> bool {
+    self.shared.lock().banned.contains(toon_name)
+  }
+
+  fn message(&self, user_id: UserID, message: &str, kind: &str) {
+    let mut state: MutexGuard<'_, State> = self.shared.lock();
+
+    let mut payload: Payload = Payload::new();
+    payload.insert("user_id", user_id);
+    payload.insert("message", message);
+    payload.insert("type", kind);
+
+    let request: RequestID = state.event_id();
+    let event: EventPacket = EventPacket::new(EventType::Message, request, payload);
+
+    state.broadcast(&event);
+  }
+}
+
+impl Debug for MockServer {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("MockServer")
+      .field("channel", &self.shared.channel)
+      .finish_non_exhaustive()
+  }
+}
+
+// =============================================================================
+// Internal Session Task
+// =============================================================================
+
+async fn serve(shared: Arc<Shared>, session: u64, mut connection: Memory) {
+  while let Some(Ok(frame)) = connection.next().await {
+    match frame {
+      Frame::Data(data) => {
+        let Ok(request) = from_str::<RequestPacket>(&data) else {
+          continue;
+        };
+
+        shared
+          .lock()
+          .handle(session, &shared.api_key, &shared.channel, request);
+      }
+      Frame::Control(Control::Ping(payload)) => {
+        let _ignore = connection.push(Frame::Control(Control::Pong(payload)));
+      }
+      Frame::Control(Control::Pong(_)) => {}
+      Frame::Close(_) => break,
+    }
+  }
+
+  shared.lock().sessions.remove(&session);
+}
+
+// =============================================================================
+// Misc. Utilities
+// =============================================================================
+
+const MODERATOR: &str = "Moderator";
+
+#[inline]
+fn user_id(payload: &Payload) -> Option<UserID> {
+  payload.get("user_id").and_then(Value::as_u64).map(UserID::new)
+}
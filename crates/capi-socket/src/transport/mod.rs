@@ -1,3 +1,15 @@
+feature! {
+  #[feature = "channel"]
+  pub mod memory;
+  pub mod record;
+  pub mod replay;
+}
+
+feature! {
+  #[feature = "tokio"]
+  pub mod mock;
+}
+
 feature! {
   #[feature = "tungstenite"]
   pub mod tungstenite;
@@ -0,0 +1,290 @@
+//! Capture a session to a JSON Lines file.
+//!
+//! A [`Recording`] wraps a [`Transport`] and writes every frame that passes
+//! through it as an [`Entry`] on its own line. Recordings can be fed back to a
+//! channel with [`Replay`][crate::transport::replay::Replay].
+
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use futures_util::Sink;
+use futures_util::Stream;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::from_str;
+use serde_json::to_string;
+use serde_json::to_writer;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::ready;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::channel::Frame;
+use crate::channel::Message;
+use crate::channel::Transport;
+
+// =============================================================================
+// Recording Entry
+// =============================================================================
+
+/// The direction of a recorded frame.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+  /// Received from the server.
+  Inbound,
+  /// Sent to the server.
+  Outbound,
+}
+
+/// A single line of a recording.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Entry {
+  /// Microseconds since the recording started.
+  pub time: u64,
+  pub direction: Direction,
+  pub frame: Frame,
+}
+
+impl Entry {
+  /// Returns the time since the recording started.
+  #[inline]
+  pub const fn elapsed(&self) -> Duration {
+    Duration::from_micros(self.time)
+  }
+}
+
+// =============================================================================
+// Recording Error
+// =============================================================================
+
+/// An error from a [`Recording`].
+#[derive(Debug)]
+pub enum RecordError<E> {
+  /// The wrapped transport failed.
+  Transport(E),
+  /// The frame could not be written to the recording.
+  Write(io::Error),
+}
+
+impl<E: Display> Display for RecordError<E> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Transport(error) => Display::fmt(error, f),
+      Self::Write(error) => write!(f, "failed to write recording: {error}"),
+    }
+  }
+}
+
+impl<E: Error + 'static> Error for RecordError<E> {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      Self::Transport(error) => Some(error),
+      Self::Write(error) => Some(error),
+    }
+  }
+}
+
+// =============================================================================
+// Recording Transport
+// =============================================================================
+
+/// A [`Transport`] that records every frame to a writer as JSON Lines.
+///
+/// The API key of authentication requests is replaced with `"<redacted>"`.
+/// Messages that cannot be classified as a [`Frame`] are not recorded.
+///
+/// Frames are written synchronously as they pass through, so the writer
+/// should be buffered; it is flushed whenever the transport is flushed.
+pub struct Recording<T, W = BufWriter<File>> {
+  transport: T,
+  writer: W,
+  start: Instant,
+}
+
+impl<T: Transport> Recording<T> {
+  /// Create a new `Recording` of `transport`, writing to the file at `path`.
+  ///
+  /// The file is created if it does not exist and truncated if it does.
+  pub fn create<P: AsRef<Path>>(transport: T, path: P) -> io::Result<Self> {
+    File::create(path).map(|file| Self::new(transport, BufWriter::new(file)))
+  }
+}
+
+impl<T: Transport, W: Write> Recording<T, W> {
+  /// Create a new `Recording` of `transport`, writing to `writer`.
+  #[inline]
+  pub fn new(transport: T, writer: W) -> Self {
+    Self {
+      transport,
+      writer,
+      start: Instant::now(),
+    }
+  }
+
+  /// Returns a reference to the wrapped transport.
+  #[inline]
+  pub const fn get_ref(&self) -> &T {
+    &self.transport
+  }
+
+  /// Consumes the recording, returning the transport and writer.
+  #[inline]
+  pub fn into_parts(self) -> (T, W) {
+    (self.transport, self.writer)
+  }
+
+  fn record(&mut self, direction: Direction, message: &T::Message) -> io::Result<()>
+  where
+    T::Message: Clone,
+  {
+    let Ok(frame) = message.clone().classify() else {
+      return Ok(());
+    };
+
+    let frame: Frame = match (direction, frame) {
+      (Direction::Outbound, Frame::Data(data)) => Frame::Data(redact(data)),
+      (_, frame) => frame,
+    };
+
+    let entry: Entry = Entry {
+      time: self.start.elapsed().as_micros().try_into().unwrap_or(u64::MAX),
+      direction,
+      frame,
+    };
+
+    to_writer(&mut self.writer, &entry)?;
+
+    self.writer.write_all(b"\n")
+  }
+}
+
+impl<T: Debug, W> Debug for Recording<T, W> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Recording")
+      .field("transport", &self.transport)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<T, W> Stream for Recording<T, W>
+where
+  T: Transport,
+  T::Message: Clone,
+  W: Write + Unpin,
+{
+  type Item = Result<T::Message, RecordError<T::Invalid>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this: &mut Self = self.get_mut();
+
+    match ready!(Pin::new(&mut this.transport).poll_next(cx)) {
+      Some(Ok(message)) => match this.record(Direction::Inbound, &message) {
+        Ok(()) => Poll::Ready(Some(Ok(message))),
+        Err(error) => Poll::Ready(Some(Err(RecordError::Write(error)))),
+      },
+      Some(Err(error)) => Poll::Ready(Some(Err(RecordError::Transport(error)))),
+      None => Poll::Ready(None),
+    }
+  }
+}
+
+impl<T, W> Sink<T::Message> for Recording<T, W>
+where
+  T: Transport,
+  T::Message: Clone,
+  W: Write + Unpin,
+{
+  type Error = RecordError<T::Error>;
+
+  #[inline]
+  fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Pin::new(&mut self.get_mut().transport)
+      .poll_ready(cx)
+      .map_err(RecordError::Transport)
+  }
+
+  fn start_send(self: Pin<&mut Self>, item: T::Message) -> Result<(), Self::Error> {
+    let this: &mut Self = self.get_mut();
+
+    this
+      .record(Direction::Outbound, &item)
+      .map_err(RecordError::Write)?;
+
+    Pin::new(&mut this.transport)
+      .start_send(item)
+      .map_err(RecordError::Transport)
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    let this: &mut Self = self.get_mut();
+
+    ready!(Pin::new(&mut this.transport).poll_flush(cx)).map_err(RecordError::Transport)?;
+
+    Poll::Ready(this.writer.flush().map_err(RecordError::Write))
+  }
+
+  fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    let this: &mut Self = self.get_mut();
+
+    ready!(Pin::new(&mut this.transport).poll_close(cx)).map_err(RecordError::Transport)?;
+
+    Poll::Ready(this.writer.flush().map_err(RecordError::Write))
+  }
+}
+
+impl<T, W> Transport for Recording<T, W>
+where
+  T: Transport,
+  T::Message: Clone,
+  W: Write + Send + Unpin + 'static,
+{
+  type Message = T::Message;
+  type Invalid = RecordError<T::Invalid>;
+
+  async fn shutdown(&mut self) -> Result<(), Self::Error> {
+    self
+      .transport
+      .shutdown()
+      .await
+      .map_err(RecordError::Transport)?;
+
+    self.writer.flush().map_err(RecordError::Write)
+  }
+}
+
+// =============================================================================
+// Misc. Utilities
+// =============================================================================
+
+/// The placeholder recorded in place of the API key.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// Replace the API key of an authentication request.
+fn redact(data: String) -> String {
+  let Ok(mut request) = from_str::<RequestPacket>(&data) else {
+    return data;
+  };
+
+  if request.command() != RequestType::Authenticate {
+    return data;
+  }
+
+  if let Some(api_key) = request.payload_mut().get_mut("api_key") {
+    *api_key = Value::from(REDACTED);
+  }
+
+  to_string(&request).unwrap_or(data)
+}
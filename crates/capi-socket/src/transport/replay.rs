@@ -0,0 +1,389 @@
+//! Feed a recorded session back to a channel.
+//!
+//! A [`Replay`] plays the inbound frames of a
+//! [`Recording`][crate::transport::record::Recording] and checks that the
+//! channel sends the same data frames the original session did.
+
+use capi_core::RequestID;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use futures_timer::Delay;
+use futures_util::Sink;
+use futures_util::Stream;
+use serde::Deserialize;
+use serde_json::from_str;
+use serde_json::to_string;
+use serde_json::to_value;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+use std::future::Future;
+use std::future::ready;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::channel::Frame;
+use crate::channel::Transport;
+use crate::transport::record::Direction;
+use crate::transport::record::Entry;
+use crate::transport::record::REDACTED;
+
+// =============================================================================
+// Replay Timing
+// =============================================================================
+
+/// When inbound frames of a [`Replay`] are delivered.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Timing {
+  /// Keep the delays of the original session.
+  #[default]
+  Original,
+  /// Divide the delays of the original session by the given factor.
+  Compressed(u32),
+  /// Deliver frames as soon as the frames sent before them were matched.
+  Immediate,
+}
+
+impl Timing {
+  fn scale(&self, elapsed: Duration) -> Option<Duration> {
+    match self {
+      Self::Original => Some(elapsed),
+      Self::Compressed(factor) => Some(elapsed / (*factor).max(1)),
+      Self::Immediate => None,
+    }
+  }
+}
+
+// =============================================================================
+// Replay Error
+// =============================================================================
+
+/// An outbound frame did not match the recording.
+#[derive(Debug)]
+pub struct ReplayError {
+  expected: Option<Frame>,
+  found: Frame,
+}
+
+impl ReplayError {
+  /// Returns the frame sent at this point of the recording, if any.
+  #[inline]
+  pub const fn expected(&self) -> Option<&Frame> {
+    self.expected.as_ref()
+  }
+
+  /// Returns the frame sent by the channel.
+  #[inline]
+  pub const fn found(&self) -> &Frame {
+    &self.found
+  }
+}
+
+impl Display for ReplayError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match self.expected {
+      Some(ref expected) => write!(f, "expected {:?}, found {:?}", expected, self.found),
+      None => write!(f, "unexpected frame after end of recording: {:?}", self.found),
+    }
+  }
+}
+
+impl Error for ReplayError {}
+
+// =============================================================================
+// Replay Transport
+// =============================================================================
+
+/// A [`Transport`] playing back a recorded session.
+///
+/// Inbound frames are delivered in order, each once all outbound data frames
+/// recorded before it have been sent, and not before its (scaled) time since
+/// the replay started. The stream ends after the last inbound frame.
+///
+/// Outbound data frames must match the recording, except for the request ID,
+/// and the API key of authentication requests. Request IDs in inbound frames
+/// are rewritten to the IDs used by the channel. Control frames are not
+/// checked, the heartbeat of the replaying channel is independent of the
+/// original session.
+#[derive(Debug)]
+pub struct Replay {
+  entries: Vec<Entry>,
+  inbound: usize,
+  outbound: usize,
+  timing: Timing,
+  start: Option<Instant>,
+  sleep: Option<Delay>,
+  requests: BTreeMap<RequestID, RequestID>,
+  waker: Option<Waker>,
+}
+
+impl Replay {
+  /// Create a new `Replay` of the given recording `entries`.
+  pub fn new(entries: Vec<Entry>) -> Self {
+    let mut this: Self = Self {
+      entries,
+      inbound: 0,
+      outbound: 0,
+      timing: Timing::Original,
+      start: None,
+      sleep: None,
+      requests: BTreeMap::new(),
+      waker: None,
+    };
+
+    this.outbound = this.next_outbound(0);
+    this
+  }
+
+  /// Create a new `Replay` of a recording read from `reader`.
+  pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+    let mut entries: Vec<Entry> = Vec::new();
+
+    for line in reader.lines() {
+      let line: String = line?;
+
+      if line.trim().is_empty() {
+        continue;
+      }
+
+      entries.push(from_str(&line).map_err(io::Error::from)?);
+    }
+
+    Ok(Self::new(entries))
+  }
+
+  /// Create a new `Replay` of the recording at `path`.
+  #[inline]
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    File::open(path).map(BufReader::new).and_then(Self::from_reader)
+  }
+
+  /// Set when inbound frames are delivered.
+  #[inline]
+  pub const fn timing(mut self, timing: Timing) -> Self {
+    self.timing = timing;
+    self
+  }
+
+  fn next_inbound(&self, from: usize) -> usize {
+    self.entries[from..]
+      .iter()
+      .position(|entry| entry.direction == Direction::Inbound)
+      .map_or(self.entries.len(), |index| from + index)
+  }
+
+  fn next_outbound(&self, from: usize) -> usize {
+    self.entries[from..]
+      .iter()
+      .position(|entry| entry.direction == Direction::Outbound && is_data(&entry.frame))
+      .map_or(self.entries.len(), |index| from + index)
+  }
+
+  /// Waits until the delay of `entry` has passed since the replay started.
+  fn poll_delay(&mut self, cx: &mut Context<'_>, entry: usize) -> Poll<()> {
+    let Some(delay) = self.timing.scale(self.entries[entry].elapsed()) else {
+      return Poll::Ready(());
+    };
+
+    let deadline: Instant = *self.start.get_or_insert_with(Instant::now) + delay;
+    let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+
+    if remaining.is_zero() {
+      return Poll::Ready(());
+    }
+
+    let sleep: &mut Delay = match self.sleep {
+      Some(ref mut sleep) => {
+        sleep.reset(remaining);
+        sleep
+      }
+      None => self.sleep.insert(Delay::new(remaining)),
+    };
+
+    Pin::new(sleep).poll(cx)
+  }
+
+  /// Rewrite the request ID of an inbound data frame.
+  fn remap(&self, frame: Frame) -> Frame {
+    let Frame::Data(data) = frame else {
+      return frame;
+    };
+
+    let Ok(mut value) = from_str::<Value>(&data) else {
+      return Frame::Data(data);
+    };
+
+    let Some(request) = value.get_mut("request_id") else {
+      return Frame::Data(data);
+    };
+
+    let Some(live) = RequestID::deserialize(&*request)
+      .ok()
+      .and_then(|id| self.requests.get(&id))
+    else {
+      return Frame::Data(data);
+    };
+
+    *request = to_value(live).unwrap_or(Value::Null);
+
+    Frame::Data(to_string(&value).unwrap_or(data))
+  }
+
+  /// Compare an outbound frame with the recording.
+  fn check(&mut self, found: Frame) -> Result<(), ReplayError> {
+    let Some(entry) = self.entries.get(self.outbound) else {
+      return Err(ReplayError {
+        expected: None,
+        found,
+      });
+    };
+
+    let matched: Option<Matched> = match (&entry.frame, &found) {
+      (Frame::Data(expected), Frame::Data(live)) => same_data(expected, live),
+      _ => None,
+    };
+
+    let Some(matched) = matched else {
+      return Err(ReplayError {
+        expected: Some(entry.frame.clone()),
+        found,
+      });
+    };
+
+    if let Matched::Request(expected, live) = matched {
+      self.requests.insert(expected, live);
+    }
+
+    self.outbound = self.next_outbound(self.outbound + 1);
+
+    if let Some(waker) = self.waker.take() {
+      waker.wake();
+    }
+
+    Ok(())
+  }
+}
+
+impl Stream for Replay {
+  type Item = Result<Frame, ReplayError>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this: &mut Self = self.get_mut();
+    let index: usize = this.next_inbound(this.inbound);
+
+    if index == this.entries.len() {
+      return Poll::Ready(None);
+    }
+
+    // Wait for the channel to catch up with the recording
+    if this.outbound < index {
+      this.waker = Some(cx.waker().clone());
+      return Poll::Pending;
+    }
+
+    if this.poll_delay(cx, index).is_pending() {
+      return Poll::Pending;
+    }
+
+    this.inbound = index + 1;
+
+    let frame: Frame = this.remap(this.entries[index].frame.clone());
+
+    Poll::Ready(Some(Ok(frame)))
+  }
+}
+
+impl Sink<Frame> for Replay {
+  type Error = ReplayError;
+
+  #[inline]
+  fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn start_send(self: Pin<&mut Self>, item: Frame) -> Result<(), Self::Error> {
+    let this: &mut Self = self.get_mut();
+
+    this.start.get_or_insert_with(Instant::now);
+
+    if is_data(&item) {
+      this.check(item)
+    } else {
+      Ok(())
+    }
+  }
+
+  #[inline]
+  fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  #[inline]
+  fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+}
+
+impl Transport for Replay {
+  type Message = Frame;
+  type Invalid = ReplayError;
+
+  #[inline]
+  fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+    ready(Ok(()))
+  }
+}
+
+// =============================================================================
+// Misc. Utilities
+// =============================================================================
+
+#[inline]
+const fn is_data(frame: &Frame) -> bool {
+  matches!(frame, Frame::Data(_))
+}
+
+/// A matching pair of data frames.
+enum Matched {
+  /// Requests with the recorded and live request IDs.
+  Request(RequestID, RequestID),
+  /// Identical frames that are not requests.
+  Other,
+}
+
+/// Compare two data frames.
+fn same_data(expected: &str, live: &str) -> Option<Matched> {
+  match (from_str::<RequestPacket>(expected), from_str::<RequestPacket>(live)) {
+    (Ok(expected), Ok(live)) if same_request(&expected, &live) => {
+      Some(Matched::Request(expected.request(), live.request()))
+    }
+    (Err(_), Err(_)) if expected == live => Some(Matched::Other),
+    _ => None,
+  }
+}
+
+/// Compare two requests, ignoring the request ID and redacted credentials.
+fn same_request(expected: &RequestPacket, live: &RequestPacket) -> bool {
+  if expected.command() != live.command() {
+    return false;
+  }
+
+  if expected.command() == RequestType::Authenticate {
+    return expected.payload().get("api_key") == Some(&Value::from(REDACTED))
+      || expected.payload() == live.payload();
+  }
+
+  expected.payload() == live.payload()
+}
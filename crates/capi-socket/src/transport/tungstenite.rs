@@ -1,26 +1,42 @@
 use std::future::Future;
+use std::io;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio::net::TcpStream;
+use tokio_tungstenite::client_async_with_config;
 use tokio_tungstenite::connect_async_with_config;
 use tokio_tungstenite::MaybeTlsStream;
 use tokio_tungstenite::WebSocketStream;
 use tokio_tungstenite::tungstenite::error::Error;
+use tokio_tungstenite::tungstenite::error::UrlError;
+use tokio_tungstenite::tungstenite::handshake::client::Response;
 use tokio_tungstenite::tungstenite::http::StatusCode;
 use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 
 use crate::ENDPOINT;
 use crate::channel;
 use crate::channel::Channel;
+use crate::channel::ChannelConfig;
+use crate::channel::ChannelError;
+use crate::channel::CloseFrame;
+use crate::channel::Connector;
+use crate::channel::Control;
+use crate::channel::Frame;
+use crate::channel::ErrorKind;
 use crate::channel::Transport;
 
 // =============================================================================
 // WebSocket Config
 // =============================================================================
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Config {
   pub(crate) endpoint: &'static str,
   pub(crate) socket: WebSocketConfig,
+  pub(crate) channel: ChannelConfig,
 }
 
 impl Config {
@@ -29,6 +45,7 @@ impl Config {
     Self {
       endpoint: ENDPOINT,
       socket: Self::default_socket_config(),
+      channel: ChannelConfig::new(),
     }
   }
 
@@ -38,6 +55,12 @@ impl Config {
     self
   }
 
+  #[inline]
+  pub fn channel(mut self, channel: ChannelConfig) -> Self {
+    self.channel = channel;
+    self
+  }
+
   #[allow(deprecated)]
   #[inline]
   const fn default_socket_config() -> WebSocketConfig {
@@ -52,43 +75,130 @@ impl Config {
   }
 }
 
+impl Default for Config {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 // =============================================================================
 // WebSocket Handler
 // =============================================================================
 
-type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+pub(crate) type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 /// Create a new socket connection.
 #[inline]
-pub async fn connect() -> Result<Channel<Socket>, Error> {
+pub async fn connect() -> Result<Channel<Socket>, ChannelError> {
   connect_with_config(Config::new()).await
 }
 
 /// Create a new socket connection with the given `config`.
-pub async fn connect_with_config(config: Config) -> Result<Channel<Socket>, Error> {
+pub async fn connect_with_config(config: Config) -> Result<Channel<Socket>, ChannelError> {
   let request: &'static str = config.endpoint;
+  let channel: ChannelConfig = config.channel;
   let config: Option<WebSocketConfig> = Some(config.socket);
 
   match connect_async_with_config(request, config, false).await {
     Ok((socket, response)) => {
-      // Sanity Check
-      assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+      check_response(&response)?;
+
+      Ok(Channel::with_config(socket, channel))
+    }
+    Err(error) => Err(connect_error(error)),
+  }
+}
+
+/// Create a new socket connection over an established `stream`.
+///
+/// See [`connect_stream_with_config`].
+#[inline]
+pub async fn connect_stream<S>(stream: S) -> Result<Channel<WebSocketStream<S>>, ChannelError>
+where
+  S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+  connect_stream_with_config(stream, Config::new()).await
+}
+
+/// Create a new socket connection over an established `stream` with the
+/// given `config`.
+///
+/// Performs the WebSocket client handshake on `stream`, e.g. a Unix socket, an
+/// in-memory pipe, or a proxied connection. The endpoint of `config` is only
+/// used for the handshake request; TLS is not negotiated, a `wss` endpoint
+/// expects `stream` to be encrypted already.
+pub async fn connect_stream_with_config<S>(
+  stream: S,
+  config: Config,
+) -> Result<Channel<WebSocketStream<S>>, ChannelError>
+where
+  S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+  let request: &'static str = config.endpoint;
+  let channel: ChannelConfig = config.channel;
+  let config: Option<WebSocketConfig> = Some(config.socket);
+
+  match client_async_with_config(request, stream, config).await {
+    Ok((socket, response)) => {
+      check_response(&response)?;
 
-      Ok(Channel::new(socket))
+      Ok(Channel::with_config(socket, channel))
     }
-    Err(error) => {
-      Err(error)
+    Err(error) => Err(connect_error(error)),
+  }
+}
+
+/// Classify a failed connection attempt.
+///
+/// Failures to reach the server are [`Transport`][ErrorKind::Transport]
+/// errors, a refused WebSocket upgrade is a [`Handshake`][ErrorKind::Handshake]
+/// error.
+#[inline]
+fn connect_error(error: Error) -> ChannelError {
+  match error {
+    Error::Io(_) | Error::Tls(_) | Error::Url(UrlError::UnableToConnect(_)) => {
+      ChannelError::new(ErrorKind::Transport, error)
     }
+    _ => ChannelError::new(ErrorKind::Handshake, error),
   }
 }
 
-impl Transport for Socket {
+#[inline]
+fn check_response(response: &Response) -> Result<(), ChannelError> {
+  // Sanity Check
+  if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+    return Err(ChannelError::msg(
+      ErrorKind::Handshake,
+      format_args!("unexpected status code: {}", response.status()),
+    ));
+  }
+
+  Ok(())
+}
+
+impl Connector for Config {
+  type Transport = Socket;
+
+  #[inline]
+  fn connect(&self) -> impl Future<Output = Result<Channel<Socket>, ChannelError>> + Send + '_ {
+    connect_with_config(self.clone())
+  }
+}
+
+impl<S> Transport for WebSocketStream<S>
+where
+  S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
   type Message = Message;
   type Invalid = Error;
 
   #[inline]
   fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
-    self.close(None)
+    self.close(Some(tungstenite::protocol::CloseFrame {
+      code: CloseCode::Normal,
+      reason: "".into(),
+    }))
   }
 }
 
@@ -100,15 +210,45 @@ impl channel::Message for Message {
     Self::Text(string)
   }
 
+  #[inline]
+  fn pong(_payload: Vec<u8>) -> Option<Self> {
+    // Note: tungstenite queues a pong reply when reading a ping frame.
+    None
+  }
+
+  #[inline]
+  fn ping(payload: Vec<u8>) -> Option<Self> {
+    Some(Message::Ping(payload))
+  }
+
   #[inline]
   fn into_string(self) -> Result<String, Self::Error> {
+    match self.classify()? {
+      Frame::Data(data) => Ok(data),
+      Frame::Control(_) | Frame::Close(_) => Err(Error::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "expected data, found control or close frame",
+      ))),
+    }
+  }
+
+  #[inline]
+  fn classify(self) -> Result<Frame, Self::Error> {
     match self {
-      Message::Text(data) => Ok(data),
-      Message::Binary(data) => String::from_utf8(data).map_err(Into::into),
-      Message::Ping(_data) => panic!("[tungstenite][message::ping]"),
-      Message::Pong(_data) => panic!("[tungstenite][message::pong]"),
-      Message::Close(_data) => panic!("[tungstenite][message::close]"),
-      Message::Frame(data) => data.into_string().map_err(Into::into),
+      Message::Text(data) => Ok(Frame::Data(data)),
+      Message::Binary(data) => String::from_utf8(data).map(Frame::Data).map_err(Into::into),
+      Message::Ping(data) => Ok(Frame::Control(Control::Ping(data))),
+      Message::Pong(data) => Ok(Frame::Control(Control::Pong(data))),
+      Message::Close(data) => Ok(Frame::Close(data.map(close_frame))),
+      Message::Frame(data) => data.into_string().map(Frame::Data).map_err(Into::into),
     }
   }
 }
+
+#[inline]
+fn close_frame(frame: tungstenite::protocol::CloseFrame<'_>) -> CloseFrame {
+  CloseFrame {
+    code: frame.code.into(),
+    reason: frame.reason.into_owned(),
+  }
+}
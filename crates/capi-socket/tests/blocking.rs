@@ -0,0 +1,173 @@
+//! The blocking client against a local WebSocket server.
+
+#![cfg(feature = "blocking")]
+
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::Payload;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_socket::blocking::Client;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::Shutdown;
+use capi_socket::transport::tungstenite::Config;
+use futures::SinkExt;
+use futures::StreamExt;
+use serde_json::from_str;
+use serde_json::to_string;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::runtime::Builder;
+use tokio::runtime::Runtime;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// What the server saw before the connection ended.
+#[derive(Debug, Default)]
+struct Log {
+  requests: Vec<RequestType>,
+  closed: bool,
+}
+
+/// Starts a server for a single connection on a background thread.
+///
+/// Returns the endpoint to connect to, and the server thread.
+fn server() -> (&'static str, thread::JoinHandle<Log>) {
+  let (send, recv): (mpsc::Sender<u16>, mpsc::Receiver<u16>) = mpsc::channel();
+
+  let thread: thread::JoinHandle<Log> = thread::spawn(move || {
+    let runtime: Runtime = Builder::new_current_thread().enable_io().build().unwrap();
+
+    runtime.block_on(async move {
+      let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+      send.send(listener.local_addr().unwrap().port()).unwrap();
+
+      let (stream, _): (TcpStream, _) = listener.accept().await.unwrap();
+
+      serve(accept_async(stream).await.unwrap()).await
+    })
+  });
+
+  let port: u16 = recv.recv().unwrap();
+  let endpoint: String = format!("ws://127.0.0.1:{port}");
+
+  (Box::leak(endpoint.into_boxed_str()), thread)
+}
+
+/// Answers every request with an empty successful response, and connect
+/// requests with a connect event.
+async fn serve(mut socket: WebSocketStream<TcpStream>) -> Log {
+  let mut log: Log = Log::default();
+
+  while let Some(Ok(message)) = socket.next().await {
+    let data: String = match message {
+      Message::Text(data) => data,
+      Message::Close(_) => {
+        log.closed = true;
+        continue;
+      }
+      _ => continue,
+    };
+
+    let request: RequestPacket = from_str(&data).unwrap();
+    let response: ResponsePacket = ResponsePacket::new(
+      request.command().response(),
+      request.request(),
+      Payload::new(),
+      None,
+    );
+
+    log.requests.push(request.command());
+
+    let data: String = to_string(&response).unwrap();
+    socket.send(Message::Text(data)).await.unwrap();
+
+    if request.command() == RequestType::Connect {
+      let payload: Payload = Payload::from_kv("channel", CHANNEL);
+      let event: EventPacket = EventPacket::new(EventType::Connect, request.request(), payload);
+
+      let data: String = to_string(&event).unwrap();
+      socket.send(Message::Text(data)).await.unwrap();
+    }
+  }
+
+  log
+}
+
+#[test]
+fn sends_requests_and_receives_events() {
+  let (endpoint, server): (&'static str, thread::JoinHandle<Log>) = server();
+  let mut client: Client = Client::connect_with_config(Config::new().endpoint(endpoint)).unwrap();
+
+  client.send_authenticate(API_KEY).unwrap();
+  client.send_connect().unwrap();
+  client.send_message("hello").unwrap();
+
+  // The iterator ends once no event arrives in time.
+  let events: Vec<EventPacket> = client
+    .events_timeout(Duration::from_millis(100))
+    .collect::<Result<Vec<EventPacket>, ChannelError>>()
+    .unwrap();
+
+  assert_eq!(events.len(), 1);
+  assert_eq!(events[0].command(), EventType::Connect);
+
+  client.close().unwrap();
+
+  let log: Log = server.join().unwrap();
+
+  assert_eq!(
+    log.requests,
+    [
+      RequestType::Authenticate,
+      RequestType::Connect,
+      RequestType::SendMessage
+    ],
+  );
+}
+
+#[test]
+fn close_ends_the_connection() {
+  let (endpoint, server): (&'static str, thread::JoinHandle<Log>) = server();
+  let client: Client = Client::connect_with_config(Config::new().endpoint(endpoint)).unwrap();
+
+  client.send_authenticate(API_KEY).unwrap();
+
+  assert!(!client.is_finished());
+
+  client.close().unwrap();
+
+  let log: Log = server.join().unwrap();
+
+  assert!(log.closed);
+  assert_eq!(log.requests, [RequestType::Authenticate]);
+}
+
+#[test]
+fn events_end_when_the_connection_stops() {
+  let (endpoint, server): (&'static str, thread::JoinHandle<Log>) = server();
+  let mut client: Client = Client::connect_with_config(Config::new().endpoint(endpoint)).unwrap();
+
+  client.send_authenticate(API_KEY).unwrap();
+  client.send_connect().unwrap();
+
+  // The connect event arrives before the response to the next request.
+  client.send_message("sync").unwrap();
+  client.shutdown(Shutdown::new()).unwrap();
+
+  let events: Vec<Result<EventPacket, ChannelError>> = client.events().collect();
+
+  assert_eq!(events.len(), 1);
+  assert!(server.join().unwrap().closed);
+}
@@ -0,0 +1,59 @@
+//! Request handling of the channel run loop.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ChannelHandle;
+use capi_socket::channel::ErrorKind;
+use capi_socket::channel::Priority;
+use capi_socket::transport::memory;
+use capi_socket::transport::memory::Memory;
+use capi_socket::SocketExt;
+use serde_json::Value;
+
+mod common;
+
+#[tokio::test]
+async fn transport_end_rejects_pending_requests() {
+  let (client, mut server): (Memory, Memory) = memory::pair();
+  let channel: Channel<Memory> = Channel::new(client);
+
+  let (response, ()): (Result<ResponsePacket, ChannelError>, ()) =
+    tokio::join!(channel.send_authenticate("api-key"), async move {
+      common::next_request(&mut server).await.unwrap();
+      drop(server);
+    });
+
+  assert_eq!(response.unwrap_err().kind(), ErrorKind::Closed);
+}
+
+#[tokio::test]
+async fn handle_priority_applies_to_socket_ext() {
+  let (client, mut server): (Memory, Memory) = memory::pair();
+  let channel: Channel<Memory> = Channel::new(client);
+  let bulk: ChannelHandle<Memory> = channel.handle().clone().with_priority(Priority::Bulk);
+
+  // Both requests are queued before the run loop gets to pick one.
+  let (_, _, order) = tokio::join!(
+    bulk.send_message("announcement"),
+    channel.send_message("reply"),
+    async {
+      let mut order: Vec<String> = Vec::new();
+
+      while order.len() < 2 {
+        let request: RequestPacket = common::next_request(&mut server).await.unwrap();
+        let message: &Value = request.payload().get("message").unwrap();
+
+        order.push(message.as_str().unwrap().to_owned());
+        common::reply(&mut server, &request).await;
+      }
+
+      order
+    }
+  );
+
+  assert_eq!(order, ["reply", "announcement"]);
+}
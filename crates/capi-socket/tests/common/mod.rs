@@ -0,0 +1,80 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use capi_core::Payload;
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+#[cfg(feature = "tokio")]
+use capi_socket::channel::Channel;
+#[cfg(feature = "tokio")]
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::Frame;
+use capi_socket::transport::memory::Memory;
+#[cfg(feature = "tokio")]
+use capi_socket::transport::mock::MockServer;
+#[cfg(feature = "tokio")]
+use capi_socket::SocketExt;
+use futures::SinkExt;
+use futures::StreamExt;
+use serde_json::from_str;
+use serde_json::to_string;
+
+/// The API key accepted by the test servers.
+pub const API_KEY: &str = "api-key";
+
+/// The channel joined on the test servers.
+pub const CHANNEL: &str = "Op Test";
+
+/// Connects a channel to `server`, authenticates, and enters chat.
+#[cfg(feature = "tokio")]
+pub async fn connect(server: &MockServer) -> Channel<Memory> {
+  connect_with_config(server, ChannelConfig::new()).await
+}
+
+/// Connects a channel with `config` to `server`, authenticates, and enters
+/// chat.
+#[cfg(feature = "tokio")]
+pub async fn connect_with_config(server: &MockServer, config: ChannelConfig) -> Channel<Memory> {
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  channel
+}
+
+/// Waits for the next request sent to `server`.
+///
+/// Returns `None` once the client is gone.
+pub async fn next_request(server: &mut Memory) -> Option<RequestPacket> {
+  loop {
+    match server.next().await? {
+      Ok(Frame::Data(data)) => return Some(from_str(&data).unwrap()),
+      Ok(Frame::Control(_)) => continue,
+      Ok(Frame::Close(_)) | Err(_) => return None,
+    }
+  }
+}
+
+/// Answer `request` on `server` with an empty successful response.
+pub async fn reply(server: &mut Memory, request: &RequestPacket) {
+  let response: ResponsePacket = ResponsePacket::new(
+    request.command().response(),
+    request.request(),
+    Payload::new(),
+    None,
+  );
+
+  let data: String = to_string(&response).unwrap();
+
+  // The client may be gone, which ends the caller's loop anyway.
+  let _ignore = server.send(Frame::Data(data)).await;
+}
+
+/// Answers every request on `server` with an empty successful response.
+pub async fn respond(mut server: Memory) {
+  while let Some(request) = next_request(&mut server).await {
+    reply(&mut server, &request).await;
+  }
+}
@@ -0,0 +1,127 @@
+//! Transports written against earlier versions of the transport traits.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::Control;
+use capi_socket::channel::Frame;
+use capi_socket::channel::Message;
+use capi_socket::channel::NotData;
+use capi_socket::channel::Transport;
+use capi_socket::transport::memory;
+use capi_socket::transport::memory::Disconnected;
+use capi_socket::transport::memory::Memory;
+use capi_socket::SocketExt;
+use futures::Sink;
+use futures::Stream;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::ready;
+use std::task::Context;
+use std::task::Poll;
+
+mod common;
+
+/// A message implementing only the methods of the original trait.
+struct Text(String);
+
+impl Message for Text {
+  type Error = Infallible;
+
+  fn from_string(string: String) -> Self {
+    Self(string)
+  }
+
+  #[allow(deprecated)]
+  fn into_string(self) -> Result<String, Self::Error> {
+    Ok(self.0)
+  }
+}
+
+/// A transport of [`Text`] messages over a memory pipe.
+struct Legacy(Memory);
+
+impl Stream for Legacy {
+  type Item = Result<Text, Disconnected>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    loop {
+      match ready!(Pin::new(&mut self.0).poll_next(cx)) {
+        Some(Ok(Frame::Data(data))) => return Poll::Ready(Some(Ok(Text(data)))),
+        Some(Ok(_)) => continue,
+        Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+        None => return Poll::Ready(None),
+      }
+    }
+  }
+}
+
+impl Sink<Text> for Legacy {
+  type Error = Disconnected;
+
+  fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Pin::new(&mut self.0).poll_ready(cx)
+  }
+
+  fn start_send(mut self: Pin<&mut Self>, item: Text) -> Result<(), Self::Error> {
+    Pin::new(&mut self.0).start_send(Frame::Data(item.0))
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Pin::new(&mut self.0).poll_flush(cx)
+  }
+
+  fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Pin::new(&mut self.0).poll_close(cx)
+  }
+}
+
+impl Transport for Legacy {
+  type Message = Text;
+  type Invalid = Disconnected;
+
+  fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+    Transport::shutdown(&mut self.0)
+  }
+}
+
+#[tokio::test]
+async fn message_without_frame_methods() {
+  let (client, server): (Memory, Memory) = memory::pair();
+  let channel: Channel<Legacy> = Channel::new(Legacy(client));
+
+  tokio::spawn(common::respond(server));
+
+  let response: Result<ResponsePacket, ChannelError> = channel.send_authenticate("api-key").await;
+
+  assert!(response.is_ok());
+}
+
+#[test]
+#[allow(deprecated)]
+fn into_string_rejects_control_frames() {
+  let ping: Frame = Frame::Control(Control::Ping(Vec::new()));
+
+  assert_eq!(
+    Frame::Data("data".to_owned()).into_string().unwrap(),
+    "data"
+  );
+  assert_eq!(ping.clone().into_string(), Err(NotData(ping)));
+  assert!(Frame::Close(None).into_string().is_err());
+}
+
+#[cfg(feature = "tungstenite")]
+#[test]
+#[allow(deprecated)]
+fn tungstenite_into_string_rejects_control_frames() {
+  use tokio_tungstenite::tungstenite;
+
+  let text: tungstenite::Message = tungstenite::Message::Text("data".to_owned());
+  let ping: tungstenite::Message = tungstenite::Message::Ping(b"ping".to_vec());
+
+  assert_eq!(Message::into_string(text).unwrap(), "data");
+  assert!(Message::into_string(ping).is_err());
+}
@@ -0,0 +1,129 @@
+//! Running a channel without a tokio runtime.
+
+#![cfg(feature = "channel")]
+
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::Frame;
+use capi_socket::channel::LocalTransport;
+use capi_socket::channel::Spawner;
+use capi_socket::channel::Transport;
+use capi_socket::transport::memory;
+use capi_socket::transport::memory::Disconnected;
+use capi_socket::transport::memory::Memory;
+use capi_socket::SocketExt;
+use futures::executor::block_on;
+use futures::executor::ThreadPool;
+use futures::future::join3;
+use futures::Sink;
+use futures::SinkExt;
+use futures::Stream;
+use futures::StreamExt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+mod common;
+
+#[test]
+fn runs_on_futures_executor() {
+  let pool: ThreadPool = ThreadPool::new().unwrap();
+  let executor: ThreadPool = pool.clone();
+
+  let spawner: Arc<dyn Spawner> = Arc::new(move |future| {
+    executor.spawn_ok(future);
+  });
+
+  let (client, server): (Memory, Memory) = memory::pair();
+
+  pool.spawn_ok(common::respond(server));
+
+  block_on(async move {
+    let channel: Channel<Memory> = Channel::with_spawner(client, ChannelConfig::new(), spawner);
+
+    let response: ResponsePacket = channel.send_authenticate("api-key").await.unwrap();
+    assert_eq!(response.status(), None);
+
+    let response: ResponsePacket = channel.send_connect().await.unwrap();
+    assert_eq!(response.status(), None);
+
+    channel.stop().await.unwrap();
+    channel.join().await.unwrap();
+  });
+}
+
+// =============================================================================
+// Local Transport
+// =============================================================================
+
+/// A [`Memory`] transport that cannot be sent to another thread.
+struct Local {
+  inner: Memory,
+  _local: PhantomData<Rc<()>>,
+}
+
+impl Stream for Local {
+  type Item = Result<Frame, Disconnected>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.inner.poll_next_unpin(cx)
+  }
+}
+
+impl Sink<Frame> for Local {
+  type Error = Disconnected;
+
+  fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_ready_unpin(cx)
+  }
+
+  fn start_send(mut self: Pin<&mut Self>, item: Frame) -> Result<(), Self::Error> {
+    self.inner.start_send_unpin(item)
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_flush_unpin(cx)
+  }
+
+  fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_close_unpin(cx)
+  }
+}
+
+impl LocalTransport for Local {
+  type Message = Frame;
+  type Invalid = Disconnected;
+
+  fn shutdown(&mut self) -> impl Future<Output = Result<(), Self::Error>> + '_ {
+    Transport::shutdown(&mut self.inner)
+  }
+}
+
+#[test]
+fn drives_local_transport_inline() {
+  let (client, server): (Memory, Memory) = memory::pair();
+
+  let transport: Local = Local {
+    inner: client,
+    _local: PhantomData,
+  };
+
+  let (channel, driver) = Channel::with_driver(transport, ChannelConfig::new());
+
+  let client = async move {
+    let response: ResponsePacket = channel.send_authenticate("api-key").await.unwrap();
+    assert_eq!(response.status(), None);
+
+    channel.stop().await.unwrap();
+    channel.join().await.unwrap();
+  };
+
+  block_on(async move {
+    join3(driver, common::respond(server), client).await;
+  });
+}
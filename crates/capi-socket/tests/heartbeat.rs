@@ -0,0 +1,120 @@
+//! Keepalive pings of the channel run loop.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::packet::ChatSendMessage;
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::Control;
+use capi_socket::channel::ErrorKind;
+use capi_socket::channel::Frame;
+use capi_socket::channel::Heartbeat;
+use capi_socket::transport::memory;
+use capi_socket::transport::memory::Memory;
+use capi_socket::SocketExt;
+use futures::SinkExt;
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::time::interval;
+use tokio::time::timeout;
+use tokio::time::Interval;
+
+mod common;
+
+/// Connects a channel that pings every 20ms and gives up after two misses.
+fn connect(client: Memory) -> Channel<Memory> {
+  let heartbeat: Heartbeat = Heartbeat::new(Duration::from_millis(20)).misses(2);
+  let config: ChannelConfig = ChannelConfig::new().heartbeat(Some(heartbeat));
+
+  Channel::with_config(client, config)
+}
+
+#[test]
+fn degenerate_settings_are_clamped() {
+  let heartbeat: Heartbeat = Heartbeat::new(Duration::ZERO).misses(0);
+
+  assert_eq!(heartbeat.interval(), Heartbeat::MIN_INTERVAL);
+}
+
+#[tokio::test]
+async fn unanswered_pings_kill_the_connection() {
+  let (client, server): (Memory, Memory) = memory::pair();
+  let channel: Channel<Memory> = connect(client);
+
+  // The server never sends anything, so every ping goes unanswered.
+  let error: ChannelError = timeout(Duration::from_secs(5), channel.join())
+    .await
+    .expect("the heartbeat never gave up")
+    .unwrap_err();
+
+  assert_eq!(error.kind(), ErrorKind::Dead);
+
+  drop(server);
+}
+
+#[tokio::test]
+async fn answered_pings_report_latency() {
+  let (client, mut server): (Memory, Memory) = memory::pair();
+  let channel: Channel<Memory> = connect(client);
+
+  assert_eq!(channel.latency(), None);
+
+  tokio::spawn(async move {
+    while let Some(Ok(frame)) = server.next().await {
+      if let Frame::Control(Control::Ping(payload)) = frame {
+        if server
+          .send(Frame::Control(Control::Pong(payload)))
+          .await
+          .is_err()
+        {
+          break;
+        }
+      }
+    }
+  });
+
+  let latency: Duration = timeout(Duration::from_secs(5), async {
+    loop {
+      if let Some(latency) = channel.latency() {
+        break latency;
+      }
+
+      tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+  })
+  .await
+  .expect("no ping was answered");
+
+  assert!(latency < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn inbound_frames_keep_the_connection_alive() {
+  let (client, mut server): (Memory, Memory) = memory::pair();
+  let channel: Channel<Memory> = connect(client);
+
+  // The server never answers pings, but keeps sending unrelated responses.
+  tokio::spawn(async move {
+    let stray: RequestPacket = RequestPacket::new(ChatSendMessage { message: "stray" });
+    let mut ticks: Interval = interval(Duration::from_millis(5));
+
+    loop {
+      tokio::select! {
+        _ = ticks.tick() => common::reply(&mut server, &stray).await,
+        request = common::next_request(&mut server) => match request {
+          Some(request) => common::reply(&mut server, &request).await,
+          None => break,
+        },
+      }
+    }
+  });
+
+  tokio::time::sleep(Duration::from_millis(200)).await;
+
+  let response: Result<ResponsePacket, ChannelError> = channel.send_authenticate("api-key").await;
+
+  assert!(response.is_ok());
+}
@@ -0,0 +1,169 @@
+//! Client-side rate limiting against the mock server.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ChannelHandle;
+use capi_socket::channel::Priority;
+use capi_socket::channel::RateLimit;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::sleep;
+use tokio::time::timeout;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+#[tokio::test]
+async fn zero_capacity_still_sends() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let limit: RateLimit = RateLimit::new(0, Duration::from_millis(10));
+  let config: ChannelConfig = ChannelConfig::new().rate_limit(Some(limit));
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  assert_eq!(limit.capacity(), 1);
+
+  let requests = async {
+    channel.send_authenticate(API_KEY).await?;
+    channel.send_connect().await?;
+    channel.send_disconnect().await
+  };
+
+  let response: Result<ResponsePacket, ChannelError> = timeout(Duration::from_secs(5), requests)
+    .await
+    .expect("limiter stalled");
+
+  assert!(response.is_ok());
+}
+
+#[tokio::test]
+async fn penalty_saturates_long_periods() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let limit: RateLimit = RateLimit::new(1, Duration::MAX);
+  let config: ChannelConfig = ChannelConfig::new()
+    .rate_limit(Some(limit))
+    .rate_limit_retries(0);
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  server.respond_with(RequestType::Authenticate, ResponseStatus::RATE_LIMITED);
+
+  let error: ChannelError = channel.send_authenticate(API_KEY).await.unwrap_err();
+
+  assert_eq!(error.response_status(), Some(ResponseStatus::RATE_LIMITED));
+
+  // The bucket is empty and penalized, the next request waits "forever".
+  let waiting = timeout(
+    Duration::from_millis(50),
+    channel.send_authenticate(API_KEY),
+  );
+
+  assert!(waiting.await.is_err());
+}
+
+#[tokio::test]
+async fn tokens_go_to_the_highest_priority() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let limit: RateLimit = RateLimit::new(1, Duration::from_millis(100));
+  let config: ChannelConfig = ChannelConfig::new().rate_limit(Some(limit));
+  let channel: Channel<Memory> = server.connect_with_config(config);
+  let bulk: ChannelHandle<Memory> = channel.handle().clone().with_priority(Priority::Bulk);
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  // "held" waits for a token, the others are queued meanwhile, lowest
+  // priority first.
+  let (first, held, (announcement, urgent)) = tokio::join!(
+    channel.send_message("first"),
+    channel.send_message("held"),
+    async {
+      sleep(Duration::from_millis(20)).await;
+
+      tokio::join!(
+        bulk.send_message("announcement"),
+        channel.send_message("urgent")
+      )
+    },
+  );
+
+  assert!(first.is_ok() && held.is_ok() && announcement.is_ok() && urgent.is_ok());
+
+  let requests: Vec<RequestPacket> = server.requests();
+  let order: Vec<Option<&str>> = requests[2..]
+    .iter()
+    .map(|request| request.payload().get("message").and_then(Value::as_str))
+    .collect();
+
+  assert_eq!(
+    order,
+    [
+      Some("first"),
+      Some("held"),
+      Some("urgent"),
+      Some("announcement")
+    ]
+  );
+}
+
+#[tokio::test]
+async fn dropped_requests_are_not_sent() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let limit: RateLimit = RateLimit::new(1, Duration::from_millis(100));
+  let config: ChannelConfig = ChannelConfig::new().rate_limit(Some(limit));
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  // Gives up while the request waits for a token.
+  let dropped = timeout(Duration::from_millis(20), channel.send_message("dropped"));
+
+  assert!(dropped.await.is_err());
+
+  channel.send_message("sent").await.unwrap();
+
+  let requests: Vec<RequestPacket> = server.requests();
+  let order: Vec<Option<&str>> = requests[2..]
+    .iter()
+    .map(|request| request.payload().get("message").and_then(Value::as_str))
+    .collect();
+
+  assert_eq!(order, [Some("sent")]);
+}
+
+#[tokio::test]
+async fn kill_does_not_wait_for_the_limiter() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let limit: RateLimit = RateLimit::new(1, Duration::MAX);
+  let config: ChannelConfig = ChannelConfig::new()
+    .rate_limit(Some(limit))
+    .rate_limit_retries(0);
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+
+  let requests = async {
+    tokio::join!(channel.send_connect(), async {
+      sleep(Duration::from_millis(20)).await;
+      channel.kill().await
+    })
+  };
+
+  let (waiting, killed) = timeout(Duration::from_secs(5), requests)
+    .await
+    .expect("kill waited for the limiter");
+
+  assert!(killed.is_ok());
+  assert!(waiting.is_err());
+}
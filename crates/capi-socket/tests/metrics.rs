@@ -0,0 +1,87 @@
+//! Channel metrics against the mock server.
+
+#![cfg(all(feature = "tokio", feature = "metrics"))]
+
+use capi_core::packet::ChatSendMessage;
+use capi_core::EventType;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ErrorKind;
+use capi_socket::channel::Histogram;
+use capi_socket::channel::MemoryRecorder;
+use capi_socket::channel::RateLimit;
+use capi_socket::channel::Snapshot;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+#[tokio::test]
+async fn latency_excludes_limiter_wait() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let recorder: Arc<MemoryRecorder> = Arc::new(MemoryRecorder::new());
+  let limit: RateLimit = RateLimit::new(1, Duration::from_millis(200));
+  let config: ChannelConfig = ChannelConfig::new()
+    .rate_limit(Some(limit))
+    .recorder(recorder.clone());
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  let start: Instant = Instant::now();
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  // The second request waited for the limiter to refill.
+  assert!(start.elapsed() >= Duration::from_millis(150));
+
+  let latency: &Histogram = &recorder.snapshot().latency[&RequestType::Connect];
+
+  assert_eq!(latency.count(), 1);
+  assert!(latency.sum() < Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn memory_recorder_counts() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let recorder: Arc<MemoryRecorder> = Arc::new(MemoryRecorder::new());
+  let config: ChannelConfig = ChannelConfig::new()
+    .rate_limit_retries(0)
+    .recorder(recorder.clone());
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  server.respond_with(RequestType::SendMessage, ResponseStatus::RATE_LIMITED);
+  server.hold(RequestType::SendMessage);
+
+  let limited: Result<ResponsePacket, ChannelError> = channel.send_message("limited").await;
+  let timeout: Duration = Duration::from_millis(20);
+  let held: Result<ResponsePacket, ChannelError> = channel
+    .send_with_timeout(ChatSendMessage { message: "held" }, timeout)
+    .await;
+
+  assert!(limited.is_err() && held.is_err());
+
+  let snapshot: Snapshot = recorder.snapshot();
+
+  assert_eq!(snapshot.requests[&RequestType::Authenticate], 1);
+  assert_eq!(snapshot.requests[&RequestType::Connect], 1);
+  assert_eq!(snapshot.requests[&RequestType::SendMessage], 2);
+  assert_eq!(snapshot.statuses[&(0, 0)], 2);
+  assert_eq!(snapshot.statuses[&(6, 8)], 1);
+  assert_eq!(snapshot.failures[&ErrorKind::Timeout], 1);
+  assert_eq!(snapshot.events[&EventType::Connect], 1);
+  assert_eq!(snapshot.latency[&RequestType::SendMessage].count(), 1);
+}
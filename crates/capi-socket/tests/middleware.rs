@@ -0,0 +1,191 @@
+//! Request middleware over a channel.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ChannelHandle;
+use capi_socket::channel::RateLimit;
+use capi_socket::middleware::DryRun;
+use capi_socket::middleware::Inspect;
+use capi_socket::middleware::Layered;
+use capi_socket::middleware::Retry;
+use capi_socket::middleware::Sanitize;
+use capi_socket::middleware::Stack;
+use capi_socket::middleware::Throttle;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// Returns the requests of type `command` the server received.
+fn received(server: &MockServer, command: RequestType) -> Vec<RequestPacket> {
+  server
+    .requests()
+    .into_iter()
+    .filter(|request| request.command() == command)
+    .collect()
+}
+
+#[tokio::test]
+async fn throttle_slows_down_on_rate_limited_errors() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let config: ChannelConfig = ChannelConfig::new().rate_limit_retries(0);
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  let throttle: Throttle = Throttle::new(RateLimit::new(1, Duration::from_millis(200)));
+  let stack: Stack<Layered<Throttle, ChannelHandle<Memory>>> =
+    Stack::new(channel.handle().clone()).layer(throttle);
+
+  server.respond_with(RequestType::SendMessage, ResponseStatus::RATE_LIMITED);
+
+  // The channel reports the status as an error.
+  let error: ChannelError = stack.send_message("first").await.unwrap_err();
+
+  assert_eq!(error.response_status(), Some(ResponseStatus::RATE_LIMITED));
+
+  // Without the penalty the next token is available after one period.
+  let start: Instant = Instant::now();
+
+  stack.send_message("second").await.unwrap();
+
+  assert!(start.elapsed() >= Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn throttle_clones_share_the_budget() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let first: Channel<Memory> = common::connect(&server).await;
+  let second: Channel<Memory> = common::connect(&server).await;
+
+  let throttle: Throttle = Throttle::new(RateLimit::new(1, Duration::from_millis(200)));
+  let first: Stack<Layered<Throttle, ChannelHandle<Memory>>> =
+    Stack::new(first.handle().clone()).layer(throttle.clone());
+  let second: Stack<Layered<Throttle, ChannelHandle<Memory>>> =
+    Stack::new(second.handle().clone()).layer(throttle);
+
+  first.send_message("first").await.unwrap();
+
+  // The only token was taken through the other channel.
+  let start: Instant = Instant::now();
+
+  second.send_message("second").await.unwrap();
+
+  assert!(start.elapsed() >= Duration::from_millis(150));
+}
+
+#[tokio::test]
+async fn dry_run_answers_chat_requests_locally() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = server.connect();
+  let stack: Stack<Layered<DryRun, ChannelHandle<Memory>>> =
+    Stack::new(channel.handle().clone()).layer(DryRun);
+
+  stack.send_authenticate(API_KEY).await.unwrap();
+  stack.send_connect().await.unwrap();
+
+  let response: ResponsePacket = stack.send_message("hello").await.unwrap();
+
+  assert_eq!(response.status(), None);
+  assert_eq!(received(&server, RequestType::Authenticate).len(), 1);
+  assert_eq!(received(&server, RequestType::Connect).len(), 1);
+  assert!(received(&server, RequestType::SendMessage).is_empty());
+}
+
+#[tokio::test]
+async fn sanitize_cleans_outgoing_messages() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+  let sanitize: Sanitize = Sanitize::new().max_length(Some(5));
+  let stack: Stack<Layered<Sanitize, ChannelHandle<Memory>>> =
+    Stack::new(channel.handle().clone()).layer(sanitize);
+
+  stack.send_message("he\r\nllo world").await.unwrap();
+
+  let requests: Vec<RequestPacket> = received(&server, RequestType::SendMessage);
+  let message: Option<&str> = requests[0].payload().get("message").and_then(Value::as_str);
+
+  assert_eq!(message, Some("hello"));
+}
+
+#[tokio::test]
+async fn retry_resends_with_a_new_request_id() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let config: ChannelConfig = ChannelConfig::new().rate_limit_retries(0);
+  let channel: Channel<Memory> = common::connect_with_config(&server, config).await;
+
+  let limited =
+    |error: &ChannelError| error.response_status() == Some(ResponseStatus::RATE_LIMITED);
+  let stack = Stack::new(channel.handle().clone()).layer(Retry::new(2, limited));
+
+  server.respond_with(RequestType::SendMessage, ResponseStatus::RATE_LIMITED);
+  stack.send_message("hello").await.unwrap();
+
+  let requests: Vec<RequestPacket> = received(&server, RequestType::SendMessage);
+
+  assert_eq!(requests.len(), 2);
+  assert_ne!(requests[0].request(), requests[1].request());
+}
+
+#[tokio::test]
+async fn retry_gives_up_on_other_errors() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+
+  let limited =
+    |error: &ChannelError| error.response_status() == Some(ResponseStatus::RATE_LIMITED);
+  let stack = Stack::new(channel.handle().clone()).layer(Retry::new(2, limited));
+
+  server.respond_with(RequestType::SendMessage, ResponseStatus::BAD_REQUEST);
+
+  let error: ChannelError = stack.send_message("hello").await.unwrap_err();
+
+  assert_eq!(error.response_status(), Some(ResponseStatus::BAD_REQUEST));
+  assert_eq!(received(&server, RequestType::SendMessage).len(), 1);
+}
+
+#[tokio::test]
+async fn inspect_sees_every_request_and_outcome() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+
+  let seen: Mutex<Vec<(RequestType, bool)>> = Mutex::new(Vec::new());
+  let inspect = Inspect::new(
+    |request: &RequestPacket, output: &Result<ResponsePacket, ChannelError>| {
+      seen
+        .lock()
+        .unwrap()
+        .push((request.command(), output.is_ok()));
+    },
+  );
+  let stack = Stack::new(channel.handle().clone()).layer(inspect);
+
+  server.respond_with(RequestType::SendEmote, ResponseStatus::BAD_REQUEST);
+
+  stack.send_message("hello").await.unwrap();
+  stack.send_emote("waves").await.unwrap_err();
+
+  assert_eq!(
+    *seen.lock().unwrap(),
+    [
+      (RequestType::SendMessage, true),
+      (RequestType::SendEmote, false)
+    ],
+  );
+}
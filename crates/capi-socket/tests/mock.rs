@@ -0,0 +1,52 @@
+//! The chat API state machine of the mock server.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::ResponseStatus;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelError;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// Returns the status of a failed request.
+fn status<T>(result: Result<T, ChannelError>) -> Option<ResponseStatus> {
+  result.err().and_then(|error| error.response_status())
+}
+
+#[tokio::test]
+async fn chat_requires_connect() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = server.connect();
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+
+  assert_eq!(
+    status(channel.send_message("hello").await),
+    Some(ResponseStatus::NOT_CONNECTED)
+  );
+
+  channel.send_connect().await.unwrap();
+  channel.send_message("hello").await.unwrap();
+}
+
+#[tokio::test]
+async fn wrong_api_key_is_rejected() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = server.connect();
+
+  assert_eq!(
+    status(channel.send_authenticate("wrong").await),
+    Some(ResponseStatus::BAD_REQUEST)
+  );
+
+  assert_eq!(
+    status(channel.send_connect().await),
+    Some(ResponseStatus::BAD_REQUEST)
+  );
+}
@@ -0,0 +1,119 @@
+//! Event queue overflow policies against the mock server.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::types::UserID;
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ErrorKind;
+use capi_socket::channel::Overflow;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// Connects a channel with room for a single event, and empties its queue.
+async fn connect(server: &MockServer, overflow: Overflow) -> Channel<Memory> {
+  let config: ChannelConfig = ChannelConfig::new().event_buffer(1).overflow(overflow);
+  let mut channel: Channel<Memory> = common::connect_with_config(server, config).await;
+
+  // The connect event arrives before the response to the next request.
+  channel.send_message("sync").await.unwrap();
+  channel.events().unwrap();
+
+  channel
+}
+
+/// Adds `count` users to the channel, one event each.
+fn crowd(server: &MockServer, count: u64) {
+  for user in 1..=count {
+    server.join(UserID::new(user), &format!("User#{user}"));
+  }
+}
+
+/// Returns the user IDs of the queued user update events.
+fn updated_users(channel: &mut Channel<Memory>) -> Vec<u64> {
+  let events: Vec<EventPacket> = channel.events().unwrap();
+
+  events
+    .iter()
+    .filter(|event| event.command() == EventType::UserUpdate)
+    .filter_map(|event| event.payload().get("user_id").and_then(Value::as_u64))
+    .collect()
+}
+
+#[tokio::test]
+async fn block_keeps_delivering_responses() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = connect(&server, Overflow::Block).await;
+
+  crowd(&server, 8);
+
+  let response = timeout(Duration::from_secs(5), channel.send_message("hello"))
+    .await
+    .expect("response was held back by the event queue");
+
+  assert!(response.is_ok());
+}
+
+#[tokio::test]
+async fn block_delivers_every_event() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let mut channel: Channel<Memory> = connect(&server, Overflow::Block).await;
+
+  crowd(&server, 3);
+  channel.send_message("sync").await.unwrap();
+
+  let mut users: Vec<u64> = Vec::new();
+
+  while users.len() < 3 {
+    users.extend(updated_users(&mut channel));
+    tokio::task::yield_now().await;
+  }
+
+  assert_eq!(users, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn drop_oldest_keeps_the_newest_event() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let mut channel: Channel<Memory> = connect(&server, Overflow::DropOldest).await;
+
+  crowd(&server, 3);
+  channel.send_message("sync").await.unwrap();
+
+  assert_eq!(updated_users(&mut channel), [3]);
+}
+
+#[tokio::test]
+async fn drop_newest_keeps_the_oldest_event() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let mut channel: Channel<Memory> = connect(&server, Overflow::DropNewest).await;
+
+  crowd(&server, 3);
+  channel.send_message("sync").await.unwrap();
+
+  assert_eq!(updated_users(&mut channel), [1]);
+}
+
+#[tokio::test]
+async fn error_stops_the_channel() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = connect(&server, Overflow::Error).await;
+
+  crowd(&server, 3);
+
+  let error: ChannelError = channel.join().await.unwrap_err();
+
+  assert_eq!(error.kind(), ErrorKind::Overflow);
+}
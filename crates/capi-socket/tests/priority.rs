@@ -0,0 +1,62 @@
+//! Request priorities against the mock server.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::types::UserID;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelHandle;
+use capi_socket::channel::Priority;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use serde_json::Value;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+#[tokio::test]
+async fn higher_priorities_are_sent_first() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = server.connect();
+  let bulk: ChannelHandle<Memory> = channel.handle().clone().with_priority(Priority::Bulk);
+  let user: UserID = UserID::new(1);
+
+  server.join(user, "Spammer#1");
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  // All requests are queued before the run loop gets to pick one, lowest
+  // priority first.
+  let (announcement, first, second, kick) = tokio::join!(
+    bulk.send_message("announcement"),
+    channel.send_message("first"),
+    channel.send_message("second"),
+    channel.kick_user(user),
+  );
+
+  assert!(announcement.is_ok() && first.is_ok() && second.is_ok() && kick.is_ok());
+
+  let requests: Vec<RequestPacket> = server.requests();
+  let order: Vec<(RequestType, Option<&str>)> = requests[2..]
+    .iter()
+    .map(|request| {
+      let message: Option<&str> = request.payload().get("message").and_then(Value::as_str);
+      (request.command(), message)
+    })
+    .collect();
+
+  assert_eq!(
+    order,
+    [
+      (RequestType::KickUser, None),
+      (RequestType::SendMessage, Some("first")),
+      (RequestType::SendMessage, Some("second")),
+      (RequestType::SendMessage, Some("announcement")),
+    ]
+  );
+}
@@ -0,0 +1,174 @@
+//! Recording a mock session and replaying it.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::packet::Authenticate;
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::RequestID;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::Frame;
+use capi_socket::channel::Transport;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::transport::record::Direction;
+use capi_socket::transport::record::Entry;
+use capi_socket::transport::record::Recording;
+use capi_socket::transport::replay::Replay;
+use capi_socket::transport::replay::Timing;
+use capi_socket::SocketExt;
+use futures::SinkExt;
+use futures::StreamExt;
+use serde_json::from_slice;
+use serde_json::from_str;
+use serde_json::to_string;
+use serde_json::Value;
+use std::future;
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// A writer whose contents outlive the recording.
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Shared {
+  fn contents(&self) -> Vec<u8> {
+    self.0.lock().unwrap().clone()
+  }
+}
+
+impl Write for Shared {
+  fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+    self.0.lock().unwrap().write(data)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Runs the session and returns the channel named by the connect event.
+async fn session<T: Transport>(mut channel: Channel<T>) -> Option<String> {
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+  channel.send_message("hello").await.unwrap();
+
+  // The replayed connection ends after the last recorded frame.
+  let _ignore = channel.stop().await;
+
+  let events: Vec<EventPacket> = channel
+    .event_stream()
+    .filter_map(|event| future::ready(event.ok()))
+    .collect()
+    .await;
+
+  let connect: &EventPacket = events
+    .iter()
+    .find(|event| event.command() == EventType::Connect)?;
+
+  let name: Option<&str> = connect.payload().get("channel").and_then(Value::as_str);
+
+  name.map(str::to_owned)
+}
+
+/// Records a session against the mock server.
+async fn record() -> Vec<u8> {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let writer: Shared = Shared::default();
+  let recording: Recording<Memory, Shared> = Recording::new(server.transport(), writer.clone());
+
+  assert_eq!(
+    session(Channel::new(recording)).await.as_deref(),
+    Some(CHANNEL)
+  );
+
+  writer.contents()
+}
+
+/// Returns the recorded requests sent to the server.
+fn recorded_requests(recording: &[u8]) -> Vec<RequestPacket> {
+  recording
+    .split(|byte| *byte == b'\n')
+    .filter(|line| !line.is_empty())
+    .map(|line| from_slice::<Entry>(line).unwrap())
+    .filter(|entry| entry.direction == Direction::Outbound)
+    .filter_map(|entry| match entry.frame {
+      Frame::Data(data) => Some(from_str(&data).unwrap()),
+      Frame::Control(_) | Frame::Close(_) => None,
+    })
+    .collect()
+}
+
+#[tokio::test]
+async fn recorded_session_replays() {
+  let recording: Vec<u8> = record().await;
+  let replay: Replay = Replay::from_reader(recording.as_slice())
+    .unwrap()
+    .timing(Timing::Immediate);
+
+  assert_eq!(
+    session(Channel::new(replay)).await.as_deref(),
+    Some(CHANNEL)
+  );
+}
+
+#[tokio::test]
+async fn recording_redacts_the_api_key() {
+  let recording: Vec<u8> = record().await;
+  let requests: Vec<RequestPacket> = recorded_requests(&recording);
+
+  let authenticate: &RequestPacket = requests
+    .iter()
+    .find(|request| request.command() == RequestType::Authenticate)
+    .unwrap();
+
+  assert_eq!(
+    authenticate
+      .payload()
+      .get("api_key")
+      .and_then(Value::as_str),
+    Some("<redacted>")
+  );
+  assert!(!String::from_utf8(recording).unwrap().contains(API_KEY));
+}
+
+#[tokio::test]
+async fn replay_answers_with_live_request_ids() {
+  let recording: Vec<u8> = record().await;
+  let recorded: RequestID = recorded_requests(&recording)[0].request();
+
+  let mut replay: Replay = Replay::from_reader(recording.as_slice())
+    .unwrap()
+    .timing(Timing::Immediate);
+
+  // Any API key matches the redacted one.
+  let request: RequestPacket = RequestPacket::new(Authenticate {
+    api_key: "other-key",
+  });
+
+  assert_ne!(request.request(), recorded);
+
+  replay
+    .send(Frame::Data(to_string(&request).unwrap()))
+    .await
+    .unwrap();
+
+  let Some(Ok(Frame::Data(data))) = replay.next().await else {
+    panic!("expected the recorded response");
+  };
+
+  let response: ResponsePacket = from_str(&data).unwrap();
+
+  assert_eq!(response.command(), RequestType::Authenticate.response());
+  assert_eq!(response.request(), request.request());
+}
@@ -0,0 +1,50 @@
+//! The tower service over a channel.
+
+#![cfg(all(feature = "tokio", feature = "tower"))]
+
+use capi_core::packet::ChatSendMessage;
+use capi_core::RequestPacket;
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ChannelService;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use std::future;
+use std::time::Duration;
+use tokio::time::timeout;
+use tower_service::Service;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+#[tokio::test]
+async fn readiness_reserves_a_queue_slot() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let config: ChannelConfig = ChannelConfig::new().request_buffer(1);
+  let channel: Channel<Memory> = server.connect_with_config(config);
+
+  channel.send_authenticate(API_KEY).await.unwrap();
+  channel.send_connect().await.unwrap();
+
+  let mut service: ChannelService<Memory> = ChannelService::new(channel.handle().clone());
+
+  future::poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+
+  // The only slot of the queue is held by the service.
+  let other = timeout(Duration::from_millis(50), channel.send_message("other"));
+
+  assert!(other.await.is_err());
+
+  let request: RequestPacket = RequestPacket::new(ChatSendMessage {
+    message: "reserved",
+  });
+  let response: Result<ResponsePacket, ChannelError> = service.call(request).await;
+
+  assert!(response.is_ok());
+  assert!(channel.send_message("other").await.is_ok());
+}
@@ -0,0 +1,101 @@
+//! The typestate session wrapper.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::IntoPayload;
+use capi_core::Packet;
+use capi_core::Payload;
+use capi_core::RequestID;
+use capi_core::ResponsePacket;
+use capi_core::ResponseStatus;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelError;
+use capi_socket::session::Authenticated;
+use capi_socket::session::InChat;
+use capi_socket::session::TransitionError;
+use capi_socket::session::Unauthenticated;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::Session;
+use capi_socket::Socket;
+use capi_socket::SocketResponse;
+use std::convert::Infallible;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// A socket answering every request with `status`, without reporting it as
+/// an error.
+#[derive(Debug)]
+struct Answer(ResponseStatus);
+
+impl Socket for Answer {
+  type Error = Infallible;
+
+  async fn send<T>(&self, payload: T) -> SocketResponse<Self>
+  where
+    T: Packet + IntoPayload,
+  {
+    let _payload: Payload = payload.into_payload();
+
+    Ok(ResponsePacket::new(
+      T::RES_TYPE,
+      RequestID::new(1),
+      Payload::new(),
+      Some(self.0),
+    ))
+  }
+}
+
+#[tokio::test]
+async fn session_moves_through_every_state() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let session: Session<Channel<Memory>> = Session::new(server.connect());
+
+  let session: Session<Channel<Memory>, Authenticated> =
+    session.authenticate(API_KEY).await.unwrap();
+  let session: Session<Channel<Memory>, InChat> = session.connect().await.unwrap();
+
+  session.send_message("hello").await.unwrap();
+
+  let session: Session<Channel<Memory>, Authenticated> = session.disconnect().await.unwrap();
+  let session: Session<Channel<Memory>, InChat> = session.connect().await.unwrap();
+
+  session.send_emote("waves").await.unwrap();
+}
+
+#[tokio::test]
+async fn failed_transition_returns_the_session() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let session: Session<Channel<Memory>> = Session::new(server.connect());
+
+  let error: TransitionError<Channel<Memory>, Unauthenticated> =
+    session.authenticate("wrong").await.unwrap_err();
+
+  let cause: Option<&ChannelError> = error.error();
+
+  assert_eq!(
+    cause.and_then(ChannelError::response_status),
+    Some(ResponseStatus::BAD_REQUEST)
+  );
+
+  let session: Session<Channel<Memory>> = error.into_session();
+
+  assert!(session.authenticate(API_KEY).await.is_ok());
+}
+
+#[tokio::test]
+async fn error_status_does_not_transition() {
+  let session: Session<Answer> = Session::new(Answer(ResponseStatus::BAD_REQUEST));
+  let error: TransitionError<Answer, Unauthenticated> =
+    session.authenticate(API_KEY).await.unwrap_err();
+
+  assert_eq!(error.status(), Some(ResponseStatus::BAD_REQUEST));
+  assert!(error.error().is_none());
+
+  let session: Session<Answer> = Session::new(Answer(ResponseStatus::OK));
+
+  assert!(session.authenticate(API_KEY).await.is_ok());
+}
@@ -0,0 +1,141 @@
+//! Graceful shutdown against the mock server.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::packet::ChatSendMessage;
+use capi_core::RequestType;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ChannelHandle;
+use capi_socket::channel::ErrorKind;
+use capi_socket::channel::Heartbeat;
+use capi_socket::channel::Priority;
+use capi_socket::channel::Shutdown;
+use capi_socket::channel::ShutdownSummary;
+use capi_socket::transport::memory;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use std::time::Duration;
+use tokio::time::sleep;
+use tokio::time::timeout;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+#[tokio::test]
+async fn queued_requests_are_sent_before_shutdown() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+  let bulk: ChannelHandle<Memory> = channel.handle().clone().with_priority(Priority::Bulk);
+
+  // All three commands are queued before the run loop gets to pick one.
+  let (announcement, reply, summary) = tokio::join!(
+    bulk.send_message("announcement"),
+    channel.send_message("reply"),
+    channel.shutdown(Shutdown::new()),
+  );
+
+  let summary: ShutdownSummary = summary.unwrap();
+
+  assert!(announcement.is_ok());
+  assert!(reply.is_ok());
+  assert_eq!(summary.abandoned(), 0);
+}
+
+#[tokio::test]
+async fn unrepresentable_deadline_waits_indefinitely() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+
+  let options: Shutdown = Shutdown::new().deadline(Duration::MAX).disconnect(true);
+  let summary: ShutdownSummary = channel.shutdown(options).await.unwrap();
+
+  assert!(summary.disconnected());
+}
+
+#[tokio::test]
+async fn summary_counts_answered_requests() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+
+  server.hold(RequestType::SendMessage);
+
+  let (response, summary, ()) = tokio::join!(
+    channel.send_message("held"),
+    channel.shutdown(Shutdown::new()),
+    async {
+      sleep(Duration::from_millis(20)).await;
+      server.release();
+    },
+  );
+
+  let summary: ShutdownSummary = summary.unwrap();
+
+  assert!(response.is_ok());
+  assert_eq!(summary.completed(), 1);
+  assert_eq!(summary.abandoned(), 0);
+  assert!(!summary.disconnected());
+}
+
+#[tokio::test]
+async fn summary_counts_abandoned_requests() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+
+  server.hold(RequestType::SendMessage);
+
+  let options: Shutdown = Shutdown::new().deadline(Duration::from_millis(20));
+  let (response, summary) = tokio::join!(channel.send_message("held"), channel.shutdown(options));
+
+  let summary: ShutdownSummary = summary.unwrap();
+
+  assert_eq!(response.unwrap_err().kind(), ErrorKind::Closed);
+  assert_eq!(summary.completed(), 0);
+  assert_eq!(summary.abandoned(), 1);
+}
+
+#[tokio::test]
+async fn requests_after_shutdown_are_rejected() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+
+  // The shutdown starts first, the request would otherwise jump the queue.
+  let (summary, response) = tokio::join!(
+    channel.shutdown(Shutdown::new()),
+    channel.send_with_priority(ChatSendMessage { message: "late" }, Priority::Moderation),
+  );
+
+  assert_eq!(response.unwrap_err().kind(), ErrorKind::Closed);
+  assert!(summary.is_ok());
+  assert!(server
+    .requests()
+    .iter()
+    .all(|request| request.command() != RequestType::SendMessage));
+}
+
+#[tokio::test]
+async fn failed_connection_still_reports_the_summary() {
+  let (client, mut server): (Memory, Memory) = memory::pair();
+  let heartbeat: Heartbeat = Heartbeat::new(Duration::from_millis(20)).misses(2);
+  let config: ChannelConfig = ChannelConfig::new().heartbeat(Some(heartbeat));
+  let channel: Channel<Memory> = Channel::with_config(client, config);
+
+  // The server takes the request, then goes silent until the heartbeat gives up.
+  let options: Shutdown = Shutdown::new().deadline(Duration::MAX);
+  let (response, summary, _request) = tokio::join!(
+    channel.send_message("held"),
+    timeout(Duration::from_secs(5), channel.shutdown(options)),
+    common::next_request(&mut server),
+  );
+
+  let summary: ShutdownSummary = summary.expect("shutdown never finished").unwrap();
+  let error: ChannelError = channel.join().await.unwrap_err();
+
+  assert_eq!(response.unwrap_err().kind(), ErrorKind::Dead);
+  assert_eq!(summary.abandoned(), 1);
+  assert_eq!(error.kind(), ErrorKind::Dead);
+}
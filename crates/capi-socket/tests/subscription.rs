@@ -0,0 +1,108 @@
+//! Event subscriptions against the mock server.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::types::UserID;
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::Payload;
+use capi_core::RequestID;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelConfig;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ErrorKind;
+use capi_socket::channel::Filter;
+use capi_socket::channel::Subscription;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use futures::StreamExt;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// Returns the next item of `subscription`, failing if none arrives in time.
+async fn next(subscription: &mut Subscription) -> Result<EventPacket, ChannelError> {
+  timeout(Duration::from_secs(1), subscription.next())
+    .await
+    .expect("subscription stalled")
+    .expect("subscription ended")
+}
+
+fn user_id(event: &EventPacket) -> Option<u64> {
+  event.payload().get("user_id").and_then(Value::as_u64)
+}
+
+#[test]
+fn filter_matches() {
+  let update: EventPacket = EventPacket::new(
+    EventType::UserUpdate,
+    RequestID::new(1),
+    Payload::from_kv("user_id", 7),
+  );
+
+  let leave: EventPacket = EventPacket::new(
+    EventType::UserLeave,
+    RequestID::new(2),
+    Payload::from_kv("user_id", 8),
+  );
+
+  let seven: Filter = Filter::predicate(|event| user_id(event) == Some(7));
+  let users: Filter = Filter::events([EventType::UserUpdate, EventType::UserLeave]);
+
+  assert!(Filter::all().matches(&update) && Filter::all().matches(&leave));
+  assert!(Filter::event(EventType::UserUpdate).matches(&update));
+  assert!(!Filter::event(EventType::UserUpdate).matches(&leave));
+  assert!(users.matches(&update) && users.matches(&leave));
+  assert!(seven.matches(&update) && !seven.matches(&leave));
+}
+
+#[tokio::test]
+async fn every_subscriber_gets_its_events() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let channel: Channel<Memory> = common::connect(&server).await;
+
+  let mut all: Subscription = channel.subscribe(Filter::all());
+  let mut leaves: Subscription = channel.subscribe(Filter::event(EventType::UserLeave));
+
+  server.join(UserID::new(1), "User#1");
+  server.leave(UserID::new(1));
+
+  let first: EventPacket = next(&mut all).await.unwrap();
+  let second: EventPacket = next(&mut all).await.unwrap();
+  let leave: EventPacket = next(&mut leaves).await.unwrap();
+
+  assert_eq!(first.command(), EventType::UserUpdate);
+  assert_eq!(second.command(), EventType::UserLeave);
+  assert_eq!(leave, second);
+}
+
+#[tokio::test]
+async fn lagging_subscriber_reports_missed_events() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let config: ChannelConfig = ChannelConfig::new().subscription_buffer(1);
+  let channel: Channel<Memory> = common::connect_with_config(&server, config).await;
+
+  let mut subscription: Subscription = channel.subscribe(Filter::all());
+
+  for user in 1..=3 {
+    server.join(UserID::new(user), &format!("User#{user}"));
+  }
+
+  // The events arrive before the response to the next request.
+  channel.send_message("sync").await.unwrap();
+
+  let error: ChannelError = next(&mut subscription).await.unwrap_err();
+
+  assert_eq!(error.kind(), ErrorKind::Lagged);
+  assert_eq!(error.missed(), Some(2));
+
+  let event: EventPacket = next(&mut subscription).await.unwrap();
+
+  assert_eq!(user_id(&event), Some(3));
+}
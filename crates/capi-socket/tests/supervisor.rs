@@ -0,0 +1,227 @@
+//! Reconnecting supervisor.
+
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_socket::channel::Backoff;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::Connector;
+use capi_socket::channel::Notification;
+use capi_socket::channel::Supervisor;
+use capi_socket::transport::memory;
+use capi_socket::transport::memory::Memory;
+use capi_socket::SocketExt;
+use futures::StreamExt;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+
+use crate::common::API_KEY;
+
+/// Opens memory connections, handing the server ends to the test.
+///
+/// Each connect waits for a permit of `gate`.
+struct Pipes {
+  gate: Arc<Semaphore>,
+  servers: mpsc::UnboundedSender<Memory>,
+}
+
+impl Connector for Pipes {
+  type Transport = Memory;
+
+  async fn connect(&self) -> Result<Channel<Memory>, ChannelError> {
+    self.gate.acquire().await.unwrap().forget();
+
+    let (client, server): (Memory, Memory) = memory::pair();
+
+    self.servers.send(server).unwrap();
+
+    Ok(Channel::new(client))
+  }
+}
+
+/// Opens memory connections while `open` is set, and connections to a server
+/// that is already gone otherwise.
+struct Lines {
+  open: Arc<AtomicBool>,
+  servers: mpsc::UnboundedSender<Memory>,
+}
+
+impl Connector for Lines {
+  type Transport = Memory;
+
+  async fn connect(&self) -> Result<Channel<Memory>, ChannelError> {
+    let (client, server): (Memory, Memory) = memory::pair();
+
+    if self.open.load(Ordering::SeqCst) {
+      self.servers.send(server).unwrap();
+    }
+
+    Ok(Channel::new(client))
+  }
+}
+
+/// Answers the requests on `server`, logging their types, until the value of
+/// `hangup` changes.
+async fn answer(
+  mut server: Memory,
+  log: mpsc::UnboundedSender<RequestType>,
+  mut hangup: watch::Receiver<u32>,
+) {
+  loop {
+    tokio::select! {
+      request = common::next_request(&mut server) => match request {
+        Some(request) => {
+          log.send(request.command()).unwrap();
+          common::reply(&mut server, &request).await;
+        }
+        None => break,
+      },
+      _ = hangup.changed() => break,
+    }
+  }
+}
+
+/// Waits for the next notification other than an event or error.
+async fn lifecycle(supervisor: &mut Supervisor<Lines>) -> Option<Notification> {
+  loop {
+    let next: Option<Result<Notification, ChannelError>> =
+      timeout(Duration::from_secs(5), supervisor.event_stream().next())
+        .await
+        .expect("supervisor stalled");
+
+    match next? {
+      Ok(Notification::Event(_)) | Err(_) => continue,
+      Ok(notification) => return Some(notification),
+    }
+  }
+}
+
+#[tokio::test]
+async fn lost_connection_is_restored() {
+  let open: Arc<AtomicBool> = Arc::new(AtomicBool::new(true));
+  let (servers, mut accepted) = mpsc::unbounded_channel();
+  let (log, mut requests) = mpsc::unbounded_channel();
+  let (hangup, _) = watch::channel(0);
+
+  let lines: Lines = Lines {
+    open: Arc::clone(&open),
+    servers,
+  };
+
+  let listen: watch::Sender<u32> = hangup.clone();
+
+  tokio::spawn(async move {
+    while let Some(server) = accepted.recv().await {
+      tokio::spawn(answer(server, log.clone(), listen.subscribe()));
+    }
+  });
+
+  let backoff: Backoff = Backoff::new()
+    .initial(Duration::from_millis(1))
+    .attempts(Some(2))
+    .jitter(false);
+
+  let mut supervisor: Supervisor<Lines> =
+    Supervisor::connect(lines, API_KEY, backoff).await.unwrap();
+
+  supervisor.send_connect().await.unwrap();
+
+  assert_eq!(requests.recv().await, Some(RequestType::Authenticate));
+  assert_eq!(requests.recv().await, Some(RequestType::Connect));
+
+  // Lose the connection, the session is restored on a new one
+  hangup.send_modify(|generation| *generation += 1);
+
+  assert!(matches!(
+    lifecycle(&mut supervisor).await,
+    Some(Notification::Reconnecting { attempt: 1, .. })
+  ));
+
+  assert!(matches!(
+    lifecycle(&mut supervisor).await,
+    Some(Notification::Restored)
+  ));
+  assert_eq!(requests.recv().await, Some(RequestType::Authenticate));
+  assert_eq!(requests.recv().await, Some(RequestType::Connect));
+
+  // Lose it again, with the server gone for good
+  open.store(false, Ordering::SeqCst);
+  hangup.send_modify(|generation| *generation += 1);
+
+  let mut attempts: Vec<u32> = Vec::new();
+
+  let gave_up: Option<Notification> = loop {
+    match lifecycle(&mut supervisor).await {
+      Some(Notification::Reconnecting { attempt, .. }) => attempts.push(attempt),
+      notification => break notification,
+    }
+  };
+
+  assert_eq!(attempts, [1, 2]);
+  assert!(matches!(gave_up, Some(Notification::GaveUp)));
+  assert!(lifecycle(&mut supervisor).await.is_none());
+}
+
+#[tokio::test]
+async fn stop_while_reconnecting_abandons_the_reconnect() {
+  let gate: Arc<Semaphore> = Arc::new(Semaphore::new(1));
+  let (servers, mut accepted) = mpsc::unbounded_channel();
+
+  let pipes: Pipes = Pipes {
+    gate: Arc::clone(&gate),
+    servers,
+  };
+
+  let backoff: Backoff = Backoff::new()
+    .initial(Duration::from_millis(1))
+    .jitter(false);
+
+  // Answer the initial authenticate request only
+  let serve = async {
+    let mut server: Memory = accepted.recv().await.unwrap();
+    let request: RequestPacket = common::next_request(&mut server).await.unwrap();
+
+    common::reply(&mut server, &request).await;
+
+    server
+  };
+
+  let (supervisor, server) = tokio::join!(Supervisor::connect(pipes, "api-key", backoff), serve);
+  let mut supervisor: Supervisor<Pipes> = supervisor.unwrap();
+
+  // Lose the connection
+  drop(server);
+
+  loop {
+    match supervisor.event_stream().next().await {
+      Some(Ok(Notification::Reconnecting { .. })) => break,
+      Some(_) => continue,
+      None => panic!("supervisor stopped"),
+    }
+  }
+
+  supervisor.stop().await.unwrap();
+
+  // Let the reconnect attempt proceed
+  gate.add_permits(1);
+
+  timeout(Duration::from_secs(5), supervisor.join())
+    .await
+    .unwrap()
+    .unwrap();
+
+  // A connection opened after the stop is closed without restoring the session
+  if let Ok(mut server) = accepted.try_recv() {
+    assert!(common::next_request(&mut server).await.is_none());
+  }
+}
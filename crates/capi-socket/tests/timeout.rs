@@ -0,0 +1,72 @@
+//! Request timeouts and cancellation against the mock server.
+
+#![cfg(feature = "tokio")]
+
+use capi_core::packet::ChatSendMessage;
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ErrorKind;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::common::API_KEY;
+use crate::common::CHANNEL;
+
+mod common;
+
+/// Returns the types of the events received so far.
+fn event_types(channel: &mut Channel<Memory>) -> Vec<EventType> {
+  let events: Vec<EventPacket> = channel.events().unwrap();
+  events.iter().map(|event| event.command()).collect()
+}
+
+#[tokio::test]
+async fn timeout_discards_the_late_response() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let mut channel: Channel<Memory> = common::connect(&server).await;
+
+  server.hold(RequestType::SendMessage);
+
+  let timeout: Duration = Duration::from_millis(20);
+  let error: ChannelError = channel
+    .send_with_timeout(ChatSendMessage { message: "late" }, timeout)
+    .await
+    .unwrap_err();
+
+  assert_eq!(error.kind(), ErrorKind::Timeout);
+
+  // The late response arrives before the response to the next request.
+  server.release();
+
+  let response: Result<ResponsePacket, ChannelError> = channel.send_message("next").await;
+
+  assert!(response.is_ok());
+  assert_eq!(event_types(&mut channel), [EventType::Connect]);
+}
+
+#[tokio::test]
+async fn dropped_request_is_deregistered() {
+  let server: MockServer = MockServer::new(API_KEY, CHANNEL);
+  let mut channel: Channel<Memory> = common::connect(&server).await;
+
+  server.hold(RequestType::SendMessage);
+
+  tokio::select! {
+    _ = channel.send_message("dropped") => panic!("held request was answered"),
+    () = sleep(Duration::from_millis(20)) => {}
+  }
+
+  server.release();
+
+  let response: Result<ResponsePacket, ChannelError> = channel.send_message("next").await;
+
+  assert!(response.is_ok());
+  assert_eq!(event_types(&mut channel), [EventType::Connect]);
+}
@@ -0,0 +1,98 @@
+//! Tracing output of the channel.
+
+#![cfg(all(feature = "tokio", feature = "tracing"))]
+
+use capi_socket::channel::Channel;
+use capi_socket::transport::memory::Memory;
+use capi_socket::transport::mock::MockServer;
+use capi_socket::SocketExt;
+use std::fmt::Debug;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing::span;
+use tracing::subscriber::DefaultGuard;
+use tracing::Event;
+use tracing::Metadata;
+use tracing::Subscriber;
+
+use crate::common::CHANNEL;
+
+mod common;
+
+/// An API key that is easy to spot in the output.
+const SECRET: &str = "secret-api-key";
+
+/// Writes every span and event field to a shared buffer.
+#[derive(Clone, Default)]
+struct Capture {
+  output: Arc<Mutex<String>>,
+  spans: Arc<Mutex<u64>>,
+}
+
+impl Capture {
+  fn output(&self) -> String {
+    self.output.lock().unwrap().clone()
+  }
+}
+
+impl Visit for Capture {
+  fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+    let mut output: MutexGuard<'_, String> = self.output.lock().unwrap();
+    let _ignore = writeln!(output, "{}={:?}", field.name(), value);
+  }
+}
+
+impl Subscriber for Capture {
+  fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+    true
+  }
+
+  fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+    let mut spans: MutexGuard<'_, u64> = self.spans.lock().unwrap();
+
+    span.record(&mut self.clone());
+    *spans += 1;
+
+    span::Id::from_u64(*spans)
+  }
+
+  fn record(&self, _span: &span::Id, values: &span::Record<'_>) {
+    values.record(&mut self.clone());
+  }
+
+  fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+  fn event(&self, event: &Event<'_>) {
+    event.record(&mut self.clone());
+  }
+
+  fn enter(&self, _span: &span::Id) {}
+
+  fn exit(&self, _span: &span::Id) {}
+}
+
+#[tokio::test]
+async fn api_key_is_never_logged() {
+  let capture: Capture = Capture::default();
+  let _guard: DefaultGuard = tracing::subscriber::set_default(capture.clone());
+
+  let server: MockServer = MockServer::new(SECRET, CHANNEL);
+  let channel: Channel<Memory> = server.connect();
+
+  channel.send_authenticate(SECRET).await.unwrap();
+  channel.send_connect().await.unwrap();
+  channel.send_message("hello").await.unwrap();
+  channel.stop().await.unwrap();
+
+  let output: String = capture.output();
+
+  // Requests are logged, payloads included.
+  assert!(output.contains("Authenticate"));
+  assert!(output.contains("<redacted>"));
+  assert!(output.contains("hello"));
+  assert!(!output.contains(SECRET));
+}
@@ -0,0 +1,124 @@
+//! Connecting over an established stream.
+
+#![cfg(feature = "tungstenite")]
+
+use capi_core::EventPacket;
+use capi_core::EventType;
+use capi_core::Payload;
+use capi_core::RequestPacket;
+use capi_core::RequestType;
+use capi_core::ResponsePacket;
+use capi_socket::channel::Channel;
+use capi_socket::channel::ChannelError;
+use capi_socket::channel::ErrorKind;
+use capi_socket::transport::tungstenite::connect_stream;
+use capi_socket::transport::tungstenite::connect_with_config;
+use capi_socket::transport::tungstenite::Config;
+use capi_socket::SocketExt;
+use futures::SinkExt;
+use futures::StreamExt;
+use serde_json::from_str;
+use serde_json::to_string;
+use serde_json::Value;
+use std::net::TcpListener;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::DuplexStream;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::common::CHANNEL;
+
+mod common;
+
+/// Answers every request on `stream` with an empty successful response, and
+/// connect requests with a connect event.
+async fn serve(stream: DuplexStream) {
+  let mut socket: WebSocketStream<DuplexStream> = accept_async(stream).await.unwrap();
+
+  while let Some(Ok(message)) = socket.next().await {
+    let Message::Text(data) = message else {
+      continue;
+    };
+
+    let request: RequestPacket = from_str(&data).unwrap();
+    let response: ResponsePacket = ResponsePacket::new(
+      request.command().response(),
+      request.request(),
+      Payload::new(),
+      None,
+    );
+
+    let data: String = to_string(&response).unwrap();
+    socket.send(Message::Text(data)).await.unwrap();
+
+    if request.command() == RequestType::Connect {
+      let payload: Payload = Payload::from_kv("channel", CHANNEL);
+      let event: EventPacket = EventPacket::new(EventType::Connect, request.request(), payload);
+
+      let data: String = to_string(&event).unwrap();
+      socket.send(Message::Text(data)).await.unwrap();
+    }
+  }
+}
+
+#[tokio::test]
+async fn connects_over_stream() {
+  let (local, remote): (DuplexStream, DuplexStream) = tokio::io::duplex(4096);
+
+  tokio::spawn(serve(remote));
+
+  let mut channel: Channel<WebSocketStream<DuplexStream>> = connect_stream(local).await.unwrap();
+
+  let response: ResponsePacket = channel.send_authenticate("api-key").await.unwrap();
+  assert_eq!(response.status(), None);
+
+  let response: ResponsePacket = channel.send_connect().await.unwrap();
+  assert_eq!(response.status(), None);
+
+  let event: EventPacket = channel.event_stream().next().await.unwrap().unwrap();
+
+  assert_eq!(event.command(), EventType::Connect);
+  assert_eq!(
+    event.payload().get("channel").and_then(Value::as_str),
+    Some(CHANNEL)
+  );
+
+  channel.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn unreachable_server_is_a_transport_error() {
+  // Nothing listens on the port once the listener is dropped.
+  let port: u16 = TcpListener::bind("127.0.0.1:0")
+    .unwrap()
+    .local_addr()
+    .unwrap()
+    .port();
+  let endpoint: String = format!("ws://127.0.0.1:{port}");
+  let config: Config = Config::new().endpoint(Box::leak(endpoint.into_boxed_str()));
+
+  let error: ChannelError = connect_with_config(config).await.unwrap_err();
+
+  assert_eq!(error.kind(), ErrorKind::Transport);
+}
+
+#[tokio::test]
+async fn refused_upgrade_is_a_handshake_error() {
+  let (local, mut remote): (DuplexStream, DuplexStream) = tokio::io::duplex(4096);
+
+  tokio::spawn(async move {
+    let mut buffer: [u8; 4096] = [0; 4096];
+    let _ignore = remote.read(&mut buffer).await;
+
+    remote
+      .write_all(b"HTTP/1.1 404 Not Found\r\n\r\n")
+      .await
+      .unwrap();
+  });
+
+  let error: ChannelError = connect_stream(local).await.unwrap_err();
+
+  assert_eq!(error.kind(), ErrorKind::Handshake);
+}
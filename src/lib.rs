@@ -12,6 +12,10 @@ pub mod socket {
   #[cfg(feature = "tungstenite")]
   pub use capi_socket::transport::tungstenite;
 
+  #[doc(inline)]
+  #[cfg(feature = "blocking")]
+  pub use capi_socket::blocking;
+
   #[doc(inline)]
   pub use capi_socket::Socket;
 
@@ -24,3 +28,9 @@ pub mod socket {
   #[doc(inline)]
   pub use capi_socket::ENDPOINT;
 }
+
+#[cfg(feature = "server")]
+pub mod server {
+  #[doc(inline)]
+  pub use capi_server::*;
+}